@@ -0,0 +1,317 @@
+use crate::ApiError;
+use crate::resolution::Resolution;
+use crate::store::WeatherStore;
+use crate::types::HourRecordFlux;
+use chrono::{DateTime, Duration, FixedOffset, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+// Periods that ended longer ago than this are assumed not to change, allowing for stations that
+// upload late
+const CLOSED_AFTER_HOURS: i64 = 24;
+
+// Closed periods are still read again eventually, as a separate listener can write into them
+// without this process knowing
+const CLOSED_TTL_DAYS: i64 = 7;
+
+// Memory held by cached results before the least recently used are dropped
+const MAX_BYTES: usize = 64 * 1024 * 1024;
+
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    expires: DateTime<Utc>,
+    result: Vec<HourRecordFlux>,
+    #[serde(skip)]
+    used: u64,
+    /// Whether the entry has a file in the cache directory
+    #[serde(skip)]
+    saved: bool,
+}
+
+impl CacheEntry {
+    fn bytes(&self) -> usize {
+        self.result.len() * size_of::<HourRecordFlux>()
+    }
+
+    fn overlaps(&self, first: DateTime<FixedOffset>, last: DateTime<FixedOffset>) -> bool {
+        first < self.end && last >= self.start
+    }
+}
+
+#[derive(Default)]
+struct Entries {
+    entries: HashMap<String, CacheEntry>,
+    bytes: usize,
+    uses: u64,
+}
+
+impl Entries {
+    fn insert(&mut self, key: String, entry: CacheEntry) {
+        self.bytes += entry.bytes();
+        if let Some(previous) = self.entries.insert(key, entry) {
+            self.bytes -= previous.bytes();
+        }
+    }
+
+    fn remove(&mut self, key: &str) -> Option<CacheEntry> {
+        let entry = self.entries.remove(key)?;
+        self.bytes -= entry.bytes();
+        Some(entry)
+    }
+
+    /// Removes the matching entries, returning the keys of those that were saved
+    fn remove_where(&mut self, remove: impl Fn(&CacheEntry) -> bool) -> Vec<String> {
+        let keys: Vec<String> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| remove(entry))
+            .map(|(key, _)| key.clone())
+            .collect();
+        keys.into_iter()
+            .filter(|key| self.remove(key).is_some_and(|entry| entry.saved))
+            .collect()
+    }
+
+    fn least_used(&self) -> Option<String> {
+        self.entries
+            .iter()
+            .min_by_key(|(_, entry)| entry.used)
+            .map(|(key, _)| key.clone())
+    }
+}
+
+fn key(start: DateTime<FixedOffset>, end: DateTime<FixedOffset>, resolution: Resolution) -> String {
+    format!(
+        "{}_{}_{}",
+        start.timestamp(),
+        end.timestamp(),
+        resolution.duration_literal().unwrap_or("raw")
+    )
+}
+
+fn entry_path(directory: &Path, key: &str) -> PathBuf {
+    directory.join(format!("{key}.json"))
+}
+
+/// The start and end of the period in a saved entry's file name
+fn saved_period(path: &Path) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    let name = path.file_stem()?.to_str()?;
+    let mut parts = name.split('_');
+    let start = DateTime::from_timestamp(parts.next()?.parse().ok()?, 0)?;
+    let end = DateTime::from_timestamp(parts.next()?.parse().ok()?, 0)?;
+    Some((start, end))
+}
+
+/// Caches range results for /api/past. Results for closed periods are kept for longer, and
+/// optionally saved to a directory with a file for each, while those reaching into the last day
+/// expire after the TTL.
+pub(crate) struct RangeCache {
+    ttl: Duration,
+    directory: Option<PathBuf>,
+    entries: Mutex<Entries>,
+}
+
+impl RangeCache {
+    pub(crate) fn new(ttl: Duration, directory: Option<PathBuf>) -> Self {
+        let mut entries = Entries::default();
+        if let Some(directory) = &directory {
+            if let Err(err) = std::fs::create_dir_all(directory) {
+                eprintln!("Creating query cache directory failed: {}", err);
+            }
+            for path in std::fs::read_dir(directory)
+                .into_iter()
+                .flatten()
+                .flatten()
+                .map(|file| file.path())
+            {
+                let key = path
+                    .extension()
+                    .is_some_and(|extension| extension == "json")
+                    .then(|| path.file_stem().and_then(|stem| stem.to_str()))
+                    .flatten();
+                let entry = std::fs::read(&path)
+                    .ok()
+                    .and_then(|json| serde_json::from_slice::<CacheEntry>(&json).ok());
+                match (key, entry) {
+                    (Some(key), Some(entry)) if entry.expires > Utc::now() => entries.insert(
+                        key.to_owned(),
+                        CacheEntry {
+                            saved: true,
+                            ..entry
+                        },
+                    ),
+                    _ => {
+                        let _ = std::fs::remove_file(&path);
+                    }
+                }
+            }
+        }
+        Self {
+            ttl,
+            directory,
+            entries: Mutex::new(entries),
+        }
+    }
+
+    pub(crate) async fn range(
+        &self,
+        store: &dyn WeatherStore,
+        start: DateTime<FixedOffset>,
+        end: DateTime<FixedOffset>,
+        resolution: Resolution,
+    ) -> Result<Vec<HourRecordFlux>, ApiError> {
+        let key = key(start, end, resolution);
+        if let Some(result) = self.get(&key).await {
+            return Ok(result);
+        }
+        let result = store.range(start, end, resolution).await?;
+        self.insert(key, start, end, result.clone()).await;
+        Ok(result)
+    }
+
+    async fn get(&self, key: &str) -> Option<Vec<HourRecordFlux>> {
+        let found = {
+            let mut entries = self.entries.lock().unwrap();
+            entries.uses += 1;
+            let uses = entries.uses;
+            let entry = entries.entries.get_mut(key)?;
+            if entry.expires > Utc::now() {
+                entry.used = uses;
+                Ok((entry.result.clone(), entry.saved))
+            } else {
+                Err(entries.remove(key).is_some_and(|entry| entry.saved))
+            }
+        };
+        let (result, saved) = match found {
+            Ok(found) => found,
+            Err(saved) => {
+                if saved {
+                    self.remove_saved(&[key.to_owned()]).await;
+                }
+                return None;
+            }
+        };
+
+        // kom import removes the files of the periods it writes into
+        if let (true, Some(directory)) = (saved, &self.directory)
+            && !tokio::fs::try_exists(entry_path(directory, key))
+                .await
+                .unwrap_or(false)
+        {
+            self.entries.lock().unwrap().remove(key);
+            return None;
+        }
+        Some(result)
+    }
+
+    async fn insert(
+        &self,
+        key: String,
+        start: DateTime<FixedOffset>,
+        end: DateTime<FixedOffset>,
+        result: Vec<HourRecordFlux>,
+    ) {
+        let now = Utc::now();
+        let closed = end <= now - Duration::hours(CLOSED_AFTER_HOURS);
+        let mut entry = CacheEntry {
+            start: start.to_utc(),
+            end: end.to_utc(),
+            expires: now
+                + if closed {
+                    Duration::days(CLOSED_TTL_DAYS)
+                } else {
+                    self.ttl
+                },
+            result,
+            used: 0,
+            saved: closed && self.directory.is_some(),
+        };
+        if entry.bytes() > MAX_BYTES {
+            return;
+        }
+
+        let json = entry.saved.then(|| serde_json::to_vec(&entry).unwrap());
+        let removed = {
+            let mut entries = self.entries.lock().unwrap();
+            let mut removed = entries.remove_where(|entry| entry.expires <= now);
+            while entries.bytes + entry.bytes() > MAX_BYTES {
+                let Some(least_used) = entries.least_used() else {
+                    break;
+                };
+                if entries.remove(&least_used).is_some_and(|entry| entry.saved) {
+                    removed.push(least_used);
+                }
+            }
+            entries.uses += 1;
+            entry.used = entries.uses;
+            entries.insert(key.clone(), entry);
+            removed
+        };
+        self.remove_saved(&removed).await;
+        if let (Some(json), Some(directory)) = (json, &self.directory) {
+            save(&entry_path(directory, &key), json).await;
+        }
+    }
+
+    /// Drops results that include any of the period from first to last, after observations are
+    /// written within it
+    pub(crate) async fn invalidate(
+        &self,
+        first: DateTime<FixedOffset>,
+        last: DateTime<FixedOffset>,
+    ) {
+        let removed = self
+            .entries
+            .lock()
+            .unwrap()
+            .remove_where(|entry| entry.overlaps(first, last));
+        self.remove_saved(&removed).await;
+    }
+
+    async fn remove_saved(&self, keys: &[String]) {
+        let Some(directory) = &self.directory else {
+            return;
+        };
+        for key in keys {
+            let _ = tokio::fs::remove_file(entry_path(directory, key)).await;
+        }
+    }
+}
+
+// Written alongside and renamed, so a crash part way through doesn't leave a partial file
+async fn save(path: &Path, json: Vec<u8>) {
+    let temporary = path.with_extension("tmp");
+    let saved = match tokio::fs::write(&temporary, json).await {
+        Ok(()) => tokio::fs::rename(&temporary, path).await,
+        Err(err) => Err(err),
+    };
+    if let Err(err) = saved {
+        eprintln!("Saving query cache failed: {}", err);
+    }
+}
+
+/// Removes the saved results of a server's cache directory that include any of the period from
+/// first to last, for writes made outside the server. The server notices the next time it reads
+/// them.
+pub(crate) async fn invalidate_saved(
+    directory: &Path,
+    first: DateTime<FixedOffset>,
+    last: DateTime<FixedOffset>,
+) -> std::io::Result<()> {
+    let mut files = match tokio::fs::read_dir(directory).await {
+        Ok(files) => files,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err),
+    };
+    while let Some(file) = files.next_entry().await? {
+        let path = file.path();
+        if saved_period(&path).is_some_and(|(start, end)| first < end && last >= start) {
+            tokio::fs::remove_file(&path).await?;
+        }
+    }
+    Ok(())
+}
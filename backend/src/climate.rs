@@ -0,0 +1,155 @@
+use crate::stats::month_records;
+use crate::types::{Climate, MonthAnomalies, MonthNormal, MonthRecordFlux, Normals};
+use crate::{ApiError, ServerState};
+use axum::Json;
+use axum::extract::State;
+use chrono::{Datelike, Local, Months};
+use std::sync::Arc;
+
+struct NormalAccumulator {
+    years: u32,
+    sums: Normals,
+    tempc_absolute_max: f64,
+    tempc_absolute_min: f64,
+}
+
+impl Default for NormalAccumulator {
+    fn default() -> Self {
+        Self {
+            years: 0,
+            sums: Normals::default(),
+            tempc_absolute_max: f64::MIN,
+            tempc_absolute_min: f64::MAX,
+        }
+    }
+}
+
+impl NormalAccumulator {
+    fn add(&mut self, record: &MonthRecordFlux) {
+        self.years += 1;
+        self.sums.tempc += record.tempc;
+        self.sums.tempc_mean_max += record.tempc_mean_max;
+        self.sums.tempc_mean_min += record.tempc_mean_min;
+        self.sums.humidity += record.humidity;
+        self.sums.solarradiation += record.solarradiation;
+        self.sums.totalrainmm += record.totalrainmm;
+        self.sums.raindayscount += record.raindayscount as f64;
+        self.tempc_absolute_max = self.tempc_absolute_max.max(record.tempc_absolute_max);
+        self.tempc_absolute_min = self.tempc_absolute_min.min(record.tempc_absolute_min);
+    }
+
+    fn into_normal(self, month: u32) -> MonthNormal {
+        let years = self.years.max(1) as f64;
+        let normals = match self.years {
+            0 => Normals::default(),
+            _ => Normals {
+                tempc: self.sums.tempc / years,
+                tempc_mean_max: self.sums.tempc_mean_max / years,
+                tempc_mean_min: self.sums.tempc_mean_min / years,
+                tempc_absolute_max: self.tempc_absolute_max,
+                tempc_absolute_min: self.tempc_absolute_min,
+                humidity: self.sums.humidity / years,
+                solarradiation: self.sums.solarradiation / years,
+                totalrainmm: self.sums.totalrainmm / years,
+                raindayscount: self.sums.raindayscount / years,
+            },
+        };
+        MonthNormal {
+            month,
+            years: self.years,
+            normals,
+        }
+    }
+}
+
+/// Normals for each calendar month, January first, from the months sorted by date. Only months
+/// that have ended and were recorded on every day are included, leaving out the first month of
+/// records too as it starts part way through a day.
+pub(crate) fn month_normals(months: &[MonthRecordFlux]) -> Vec<MonthNormal> {
+    let now = Local::now();
+    let mut accumulators: Vec<NormalAccumulator> =
+        (0..12).map(|_| NormalAccumulator::default()).collect();
+    for record in months.iter().skip(1) {
+        let end = record.time.checked_add_months(Months::new(1)).unwrap();
+        let days = (end.date_naive() - record.time.date_naive()).num_days();
+        if end <= now && record.dayscount == days {
+            accumulators[record.time.month0() as usize].add(record);
+        }
+    }
+    accumulators
+        .into_iter()
+        .enumerate()
+        .map(|(index, accumulator)| accumulator.into_normal(index as u32 + 1))
+        .collect()
+}
+
+/// Normals for the whole year, once every calendar month has at least one complete year
+pub(crate) fn annual_normals(months: &[MonthNormal]) -> Option<Normals> {
+    if months.iter().any(|month| month.years == 0) {
+        return None;
+    }
+    let mean = |value: fn(&Normals) -> f64| {
+        months
+            .iter()
+            .map(|month| value(&month.normals))
+            .sum::<f64>()
+            / months.len() as f64
+    };
+    let total = |value: fn(&Normals) -> f64| months.iter().map(|month| value(&month.normals)).sum();
+    Some(Normals {
+        tempc: mean(|normals| normals.tempc),
+        tempc_mean_max: mean(|normals| normals.tempc_mean_max),
+        tempc_mean_min: mean(|normals| normals.tempc_mean_min),
+        tempc_absolute_max: months
+            .iter()
+            .map(|month| month.normals.tempc_absolute_max)
+            .fold(f64::MIN, f64::max),
+        tempc_absolute_min: months
+            .iter()
+            .map(|month| month.normals.tempc_absolute_min)
+            .fold(f64::MAX, f64::min),
+        humidity: mean(|normals| normals.humidity),
+        solarradiation: mean(|normals| normals.solarradiation),
+        totalrainmm: total(|normals| normals.totalrainmm),
+        raindayscount: total(|normals| normals.raindayscount),
+    })
+}
+
+pub(crate) fn anomalies(
+    record: &MonthRecordFlux,
+    normals: &[MonthNormal],
+) -> Option<MonthAnomalies> {
+    let normal = &normals[record.time.month0() as usize];
+    if normal.years == 0 {
+        return None;
+    }
+    let normal = &normal.normals;
+    Some(MonthAnomalies {
+        tempc: record.tempc - normal.tempc,
+        tempc_mean_max: record.tempc_mean_max - normal.tempc_mean_max,
+        tempc_mean_min: record.tempc_mean_min - normal.tempc_mean_min,
+        totalrainmm: record.totalrainmm - normal.totalrainmm,
+        totalrainmm_percent: (normal.totalrainmm > 0_f64)
+            .then(|| record.totalrainmm * 100_f64 / normal.totalrainmm),
+        raindayscount: record.raindayscount as f64 - normal.raindayscount,
+    })
+}
+
+/// Statistics for every month the station has recorded
+pub(crate) async fn station_months(state: &ServerState) -> Result<Vec<MonthRecordFlux>, ApiError> {
+    Ok(month_records(&state.rollups.all(state).await?))
+}
+
+pub(crate) async fn climate(
+    State(state): State<Arc<ServerState>>,
+) -> Result<Json<Climate>, ApiError> {
+    let months = station_months(&state).await?;
+    let normals = month_normals(&months);
+
+    Ok(Json(Climate {
+        first: months.first().map(|record| record.time),
+        last: months.last().map(|record| record.time),
+        annual: annual_normals(&normals),
+        months: normals,
+    }))
+}
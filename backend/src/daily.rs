@@ -0,0 +1,137 @@
+use crate::rain::{RainDayBoundary, local_time, rain_between};
+use crate::resolution::Resolution;
+use crate::types::{DailySnapshot, DayRecord, HourRecordFlux};
+use crate::wind::cardinal;
+use crate::{ApiError, RangeParams, ServerState, parse_range_params};
+use axum::Json;
+use axum::extract::{Query, State};
+use chrono::{DateTime, Days, Duration, FixedOffset, Local, NaiveDate};
+use std::sync::Arc;
+
+// BOM observation times, in local time
+const MORNING_HOUR: u32 = 9;
+const AFTERNOON_HOUR: u32 = 15;
+
+// Snapshots use the closest observation within this many minutes of the observation time
+const SNAPSHOT_MINUTES: i64 = 15;
+
+// Gaps between observations longer than this are not counted towards solar energy or sunshine
+const MAX_GAP_MINUTES: i64 = 15;
+
+// WMO threshold for bright sunshine in W/m². Stations only measure global rather than direct
+// radiation, so sunshine hours are an estimate.
+const SUNSHINE_RADIATION: f64 = 120_f64;
+
+fn snapshot(data: &[HourRecordFlux], target: DateTime<FixedOffset>) -> Option<DailySnapshot> {
+    data.iter()
+        .filter(|datum| (datum.time - target).abs() <= Duration::minutes(SNAPSHOT_MINUTES))
+        .min_by_key(|datum| (datum.time - target).abs())
+        .map(|datum| DailySnapshot {
+            time: datum.time,
+            tempc: datum.tempc,
+            humidity: datum.humidity,
+            windspeedkph: datum.windspeedkph,
+            winddir: datum.winddir,
+            windcardinal: datum.winddir.map(|winddir| cardinal(winddir).to_string()),
+        })
+}
+
+/// Summarises a local calendar day from the observations during it, which must be sorted by time
+fn day_record(
+    date: NaiveDate,
+    day: &[HourRecordFlux],
+    all: &[HourRecordFlux],
+    rain_day: RainDayBoundary,
+) -> DayRecord {
+    // Observations without a reading are left out rather than counted as zero
+    let temperatures = || {
+        day.iter()
+            .filter_map(|datum| datum.tempc.map(|tempc| (tempc, datum)))
+    };
+    let min = temperatures().min_by(|(a, _), (b, _)| a.total_cmp(b));
+    let max = temperatures().max_by(|(a, _), (b, _)| a.total_cmp(b));
+    let gust = day
+        .iter()
+        .filter_map(|datum| datum.windgustkph.map(|gust| (gust, datum)))
+        .max_by(|(a, _), (b, _)| a.total_cmp(b));
+
+    let mut solarenergy = 0_f64;
+    let mut sunshine = Duration::zero();
+    for pair in day.windows(2) {
+        let gap = pair[1].time - pair[0].time;
+        if gap > Duration::minutes(MAX_GAP_MINUTES) {
+            continue;
+        }
+        let seconds = gap.num_seconds() as f64;
+        let (Some(first), Some(second)) = (pair[0].solarradiation, pair[1].solarradiation) else {
+            continue;
+        };
+        let radiation = (first.max(0_f64) + second.max(0_f64)) / 2_f64;
+        solarenergy += radiation * seconds / 1_000_000_f64;
+        if first >= SUNSHINE_RADIATION {
+            sunshine += gap;
+        }
+    }
+
+    let morning = local_time(date, MORNING_HOUR);
+    DayRecord {
+        date,
+        mintemp: min.map(|(tempc, _)| tempc),
+        mintemptime: min.map(|(_, datum)| datum.time),
+        maxtemp: max.map(|(tempc, _)| tempc),
+        maxtemptime: max.map(|(_, datum)| datum.time),
+        rainmm: rain_between(all, rain_day.start(date), rain_day.end(date)),
+        maxgustkph: gust.map(|(gust, _)| gust),
+        maxgustdir: gust.and_then(|(_, datum)| datum.winddir),
+        maxgustcardinal: gust
+            .and_then(|(_, datum)| datum.winddir)
+            .map(|winddir| cardinal(winddir).to_string()),
+        maxgusttime: gust.map(|(_, datum)| datum.time),
+        solarenergy,
+        sunshinehours: sunshine.num_seconds() as f64 / 3600_f64,
+        at9am: snapshot(day, morning),
+        at3pm: snapshot(day, local_time(date, AFTERNOON_HOUR)),
+    }
+}
+
+pub(crate) async fn daily(
+    State(state): State<Arc<ServerState>>,
+    Query(params): Query<RangeParams>,
+) -> Result<Json<Vec<DayRecord>>, ApiError> {
+    let (start, end) = parse_range_params(&params)?;
+    let first = start.with_timezone(&Local).date_naive();
+    let last = (end - Duration::seconds(1))
+        .with_timezone(&Local)
+        .date_naive();
+
+    // Rainfall for the first day may start the day before, and needs an earlier observation to
+    // measure the first increase from
+    let mut data = state
+        .store
+        .range(
+            state.rain_day.start(first) - Duration::minutes(MAX_GAP_MINUTES),
+            local_time(last + Days::new(1), 0),
+            Resolution::Raw,
+        )
+        .await?;
+    data.sort_by_key(|r| r.time);
+
+    let mut result = Vec::new();
+    let mut date = first;
+    while date <= last {
+        let (from, to) = (local_time(date, 0), local_time(date + Days::new(1), 0));
+        let day_start = data.partition_point(|datum| datum.time < from);
+        let day_end = data.partition_point(|datum| datum.time < to);
+        if day_start < day_end {
+            result.push(day_record(
+                date,
+                &data[day_start..day_end],
+                &data,
+                state.rain_day,
+            ));
+        }
+        date = date + Days::new(1);
+    }
+
+    Ok(Json(result))
+}
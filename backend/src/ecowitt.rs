@@ -0,0 +1,106 @@
+use crate::ingest::{parse_date_utc, parse_param, sanitise, write_observation};
+use crate::types::Observation;
+use crate::units::{fahrenheit_to_celsius, inches_to_mm, inhg_to_hpa, mph_to_kph};
+use crate::{ApiError, ServerState};
+use axum::{Form, extract::State};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+pub(crate) fn parse_ecowitt(params: &HashMap<String, String>) -> Observation {
+    Observation {
+        time: parse_date_utc(params),
+        model: params
+            .get("model")
+            .or(params.get("stationtype"))
+            .cloned()
+            .unwrap_or_default(),
+        tempc: parse_param(params, "tempf").map(fahrenheit_to_celsius),
+        tempinc: parse_param(params, "tempinf").map(fahrenheit_to_celsius),
+        humidity: parse_param(params, "humidity"),
+        humidityin: parse_param(params, "humidityin"),
+        windspeedkph: parse_param(params, "windspeedmph").map(mph_to_kph),
+        windgustkph: parse_param(params, "windgustmph").map(mph_to_kph),
+        winddir: parse_param(params, "winddir"),
+        rainratemm: parse_param(params, "rainratein").map(inches_to_mm),
+        totalrainmm: parse_param(params, "totalrainin").map(inches_to_mm),
+        dailyrainmm: parse_param(params, "dailyrainin").map(inches_to_mm),
+        eventrainmm: parse_param(params, "eventrainin").map(inches_to_mm),
+        uv: parse_param(params, "uv"),
+        solarradiation: parse_param(params, "solarradiation"),
+        baromrelhpa: parse_param(params, "baromrelin").map(inhg_to_hpa),
+        baromabshpa: parse_param(params, "baromabsin").map(inhg_to_hpa),
+    }
+}
+
+pub(crate) async fn ecowitt(
+    State(state): State<Arc<ServerState>>,
+    Form(params): Form<HashMap<String, String>>,
+) -> Result<&'static str, ApiError> {
+    if let Some(passkey) = &state.ecowitt_passkey
+        && params.get("PASSKEY") != Some(passkey)
+    {
+        return Err(ApiError::Unauthorised);
+    }
+
+    let observation = sanitise(parse_ecowitt(&params));
+    write_observation(&state, observation).await?;
+
+    Ok("OK")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(values: &[(&str, &str)]) -> HashMap<String, String> {
+        values
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn converts_to_metric() {
+        let observation = parse_ecowitt(&params(&[
+            ("PASSKEY", "abc"),
+            ("stationtype", "EasyWeatherPro_V5.1.6"),
+            ("model", "GW2000A_V3.1.4"),
+            ("dateutc", "2025-01-10 03:04:05"),
+            ("tempf", "68.0"),
+            ("humidity", "55"),
+            ("windspeedmph", "10"),
+            ("winddir", "270"),
+            ("rainratein", "0.1"),
+            ("dailyrainin", "1"),
+            ("totalrainin", "10"),
+            ("baromrelin", "29.92"),
+            ("baromabsin", "29.5"),
+            ("uv", "3"),
+        ]));
+        assert_eq!(observation.time.to_rfc3339(), "2025-01-10T03:04:05+00:00");
+        assert_eq!(observation.model, "GW2000A_V3.1.4");
+        assert!((observation.tempc.unwrap() - 20.0).abs() < 1e-9);
+        assert_eq!(observation.humidity, Some(55.0));
+        assert!((observation.windspeedkph.unwrap() - 16.09344).abs() < 1e-9);
+        assert_eq!(observation.winddir, Some(270.0));
+        assert!((observation.rainratemm.unwrap() - 2.54).abs() < 1e-9);
+        assert_eq!(observation.dailyrainmm, Some(25.4));
+        assert_eq!(observation.totalrainmm, Some(254.0));
+        assert!((observation.baromrelhpa.unwrap() - 1013.2).abs() < 0.01);
+        assert_eq!(observation.uv, Some(3.0));
+        assert_eq!(observation.tempinc, None);
+        assert_eq!(observation.eventrainmm, None);
+    }
+
+    #[test]
+    fn falls_back_to_station_type() {
+        let observation = parse_ecowitt(&params(&[
+            ("stationtype", "EasyWeatherPro_V5.1.6"),
+            ("tempf", "not a number"),
+            ("humidity", "NaN"),
+        ]));
+        assert_eq!(observation.model, "EasyWeatherPro_V5.1.6");
+        assert_eq!(observation.tempc, None);
+        assert_eq!(observation.humidity, None);
+    }
+}
@@ -0,0 +1,356 @@
+use crate::rain::{RainAccumulator, local_utc_offset};
+use crate::resolution::Resolution;
+use crate::types::HourRecordWithDerivedTypes;
+use crate::{ApiError, RangeParams, ServerState, derive_hour_records, parse_range_params};
+use arrow_array::{ArrayRef, BooleanArray, Float64Array, RecordBatch, StringArray};
+use arrow_array::{TimestampMillisecondArray, builder::Float64Builder};
+use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use axum::body::{Body, Bytes};
+use axum::extract::{Query, State};
+use axum::http::header;
+use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, Duration, FixedOffset};
+use parquet::arrow::ArrowWriter;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ExportFormat {
+    Csv,
+    Ndjson,
+    Parquet,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct ExportParams {
+    #[serde(flatten)]
+    range: RangeParams,
+    format: ExportFormat,
+    #[serde(default)]
+    resolution: Resolution,
+}
+
+// ArrowWriter owns its output, so row groups are written to a buffer shared with the stream
+// which drains it after each chunk
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl SharedBuffer {
+    fn take(&self) -> Vec<u8> {
+        std::mem::take(&mut *self.0.lock().unwrap())
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+enum Encoder {
+    Csv {
+        wrote_header: bool,
+    },
+    Ndjson,
+    Parquet {
+        buffer: SharedBuffer,
+        schema: Option<SchemaRef>,
+        writer: Option<Box<ArrowWriter<SharedBuffer>>>,
+    },
+}
+
+impl Encoder {
+    fn new(format: ExportFormat) -> Self {
+        match format {
+            ExportFormat::Csv => Encoder::Csv {
+                wrote_header: false,
+            },
+            ExportFormat::Ndjson => Encoder::Ndjson,
+            ExportFormat::Parquet => Encoder::Parquet {
+                buffer: SharedBuffer::default(),
+                schema: None,
+                writer: None,
+            },
+        }
+    }
+
+    fn encode(&mut self, records: &[HourRecordWithDerivedTypes]) -> Result<Vec<u8>, ApiError> {
+        match self {
+            Encoder::Csv { wrote_header } => {
+                let mut writer = csv::WriterBuilder::new()
+                    .has_headers(!*wrote_header)
+                    .from_writer(Vec::new());
+                for record in records {
+                    writer
+                        .serialize(record)
+                        .map_err(|err| ApiError::Other(err.to_string()))?;
+                    *wrote_header = true;
+                }
+                writer
+                    .into_inner()
+                    .map_err(|err| ApiError::Other(err.to_string()))
+            }
+            Encoder::Ndjson => {
+                let mut output = Vec::new();
+                for record in records {
+                    serde_json::to_writer(&mut output, record)
+                        .map_err(|err| ApiError::Other(err.to_string()))?;
+                    output.push(b'\n');
+                }
+                Ok(output)
+            }
+            Encoder::Parquet {
+                buffer,
+                schema,
+                writer,
+            } => {
+                if records.is_empty() {
+                    return Ok(Vec::new());
+                }
+                // Later chunks reuse the schema of the first, as a row group cannot add columns
+                let batch = record_batch(records, schema.clone())?;
+                let writer = match writer {
+                    Some(writer) => writer,
+                    None => {
+                        *schema = Some(batch.schema());
+                        writer.insert(Box::new(
+                            ArrowWriter::try_new(buffer.clone(), batch.schema(), None)
+                                .map_err(|err| ApiError::Other(err.to_string()))?,
+                        ))
+                    }
+                };
+                writer
+                    .write(&batch)
+                    .and_then(|_| writer.flush())
+                    .map_err(|err| ApiError::Other(err.to_string()))?;
+                Ok(buffer.take())
+            }
+        }
+    }
+
+    fn finish(&mut self) -> Result<Vec<u8>, ApiError> {
+        match self {
+            Encoder::Parquet { buffer, writer, .. } => {
+                let writer = match writer.take() {
+                    Some(writer) => *writer,
+                    None => {
+                        // Nothing was exported, but the response should still be a valid file
+                        let batch = record_batch(&[HourRecordWithDerivedTypes::default()], None)?;
+                        ArrowWriter::try_new(buffer.clone(), batch.schema(), None)
+                            .map_err(|err| ApiError::Other(err.to_string()))?
+                    }
+                };
+                writer
+                    .close()
+                    .map_err(|err| ApiError::Other(err.to_string()))?;
+                Ok(buffer.take())
+            }
+            _ => Ok(Vec::new()),
+        }
+    }
+}
+
+fn infer_schema(rows: &[Map<String, Value>]) -> SchemaRef {
+    let mut fields = vec![Field::new(
+        "time",
+        DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into())),
+        false,
+    )];
+    for key in rows[0].keys().filter(|key| *key != "time") {
+        let values = || rows.iter().filter_map(|row| row.get(key));
+        let data_type = if values().any(Value::is_string) {
+            DataType::Utf8
+        } else if values().any(Value::is_boolean) {
+            DataType::Boolean
+        } else {
+            DataType::Float64
+        };
+        fields.push(Field::new(key, data_type, true));
+    }
+    Arc::new(Schema::new(fields))
+}
+
+// Columns are built from the serialised records so that the Parquet output always has the same
+// fields as the CSV and NDJSON output
+fn record_batch(
+    records: &[HourRecordWithDerivedTypes],
+    schema: Option<SchemaRef>,
+) -> Result<RecordBatch, ApiError> {
+    let rows: Vec<Map<String, Value>> = records
+        .iter()
+        .map(|record| match serde_json::to_value(record) {
+            Ok(Value::Object(map)) => map,
+            _ => Map::new(),
+        })
+        .collect();
+    let schema = schema.unwrap_or_else(|| infer_schema(&rows));
+
+    let columns = schema
+        .fields()
+        .iter()
+        .map(|field| -> ArrayRef {
+            let name = field.name().as_str();
+            let values = || rows.iter().map(move |row| row.get(name));
+            match field.data_type() {
+                DataType::Timestamp(_, _) => Arc::new(
+                    TimestampMillisecondArray::from_iter_values(
+                        records.iter().map(|record| record.time.timestamp_millis()),
+                    )
+                    .with_timezone("UTC"),
+                ),
+                DataType::Utf8 => Arc::new(StringArray::from_iter(
+                    values().map(|value| value.and_then(Value::as_str)),
+                )),
+                DataType::Boolean => Arc::new(BooleanArray::from_iter(
+                    values().map(|value| value.and_then(Value::as_bool)),
+                )),
+                _ => {
+                    let mut builder = Float64Builder::with_capacity(rows.len());
+                    builder.extend(values().map(|value| value.and_then(Value::as_f64)));
+                    Arc::new(builder.finish()) as Arc<Float64Array>
+                }
+            }
+        })
+        .collect();
+
+    RecordBatch::try_new(schema, columns).map_err(|err| ApiError::Other(err.to_string()))
+}
+
+// Ranges are queried in chunks so that a year of raw data is never held in memory at once
+fn chunk_length(resolution: Resolution) -> Duration {
+    match resolution {
+        Resolution::Raw => Duration::days(1),
+        Resolution::FiveMinutes | Resolution::TenMinutes | Resolution::ThirtyMinutes => {
+            Duration::days(7)
+        }
+        Resolution::Hour => Duration::days(31),
+        Resolution::Day => Duration::days(366),
+    }
+}
+
+/// End of the chunk starting at start, moved back to the start of the window it falls in. Windows
+/// start on the local hour or day, as they do in the stores, so that none is split in two.
+fn chunk_end(
+    start: DateTime<FixedOffset>,
+    resolution: Resolution,
+    utc_offset: Duration,
+) -> DateTime<FixedOffset> {
+    let end = start + chunk_length(resolution);
+    match (resolution.seconds(), resolution.window_offset(utc_offset)) {
+        (Some(window), Some(offset)) => {
+            end - Duration::seconds((end.timestamp() - offset.num_seconds()).rem_euclid(window))
+        }
+        _ => end,
+    }
+}
+
+struct ExportCursor {
+    state: Arc<ServerState>,
+    next_start: DateTime<FixedOffset>,
+    end: DateTime<FixedOffset>,
+    resolution: Resolution,
+    rain: RainAccumulator,
+    encoder: Encoder,
+    finished: bool,
+}
+
+impl ExportCursor {
+    async fn next_chunk(&mut self) -> Result<Vec<u8>, ApiError> {
+        if self.next_start >= self.end {
+            self.finished = true;
+            return self.encoder.finish();
+        }
+
+        let chunk_end =
+            chunk_end(self.next_start, self.resolution, local_utc_offset()).min(self.end);
+
+        let mut data = self
+            .state
+            .store
+            .range(self.next_start, chunk_end, self.resolution)
+            .await?;
+        data.sort_by_key(|r| r.time);
+        self.next_start = chunk_end;
+
+        let records = derive_hour_records(data, &mut self.rain, &self.state, self.resolution);
+        self.encoder.encode(&records)
+    }
+}
+
+pub(crate) async fn export(
+    State(state): State<Arc<ServerState>>,
+    Query(params): Query<ExportParams>,
+) -> Result<Response, ApiError> {
+    let (start, end) = parse_range_params(&params.range)?;
+    let (content_type, extension) = match params.format {
+        ExportFormat::Csv => ("text/csv", "csv"),
+        ExportFormat::Ndjson => ("application/x-ndjson", "ndjson"),
+        ExportFormat::Parquet => ("application/vnd.apache.parquet", "parquet"),
+    };
+
+    let cursor = ExportCursor {
+        state,
+        next_start: start,
+        end,
+        resolution: params.resolution,
+        rain: RainAccumulator::default(),
+        encoder: Encoder::new(params.format),
+        finished: false,
+    };
+    let stream = futures::stream::unfold(cursor, |mut cursor| async move {
+        if cursor.finished {
+            return None;
+        }
+        match cursor.next_chunk().await {
+            Ok(bytes) => Some((Ok(Bytes::from(bytes)), cursor)),
+            // The headers have already been sent, so the failure can't change the status. Ending
+            // the stream with an error aborts the response instead of finishing it, so the client
+            // sees an incomplete download rather than a truncated file.
+            Err(err) => {
+                eprintln!("Export failed part way: {}", err);
+                cursor.finished = true;
+                Some((Err(err), cursor))
+            }
+        }
+    });
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"kom-export.{extension}\""),
+            ),
+        ],
+        Body::from_stream(stream),
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunks_end_on_local_windows() {
+        let start = DateTime::parse_from_rfc3339("2025-03-01T10:17:00+05:45").unwrap();
+        let offset = Duration::minutes(345);
+
+        let end = chunk_end(start, Resolution::Hour, offset);
+        assert_eq!(end.to_rfc3339(), "2025-04-01T10:00:00+05:45");
+        let end = chunk_end(start, Resolution::Day, offset);
+        assert_eq!(end.to_rfc3339(), "2026-03-02T00:00:00+05:45");
+        let end = chunk_end(start, Resolution::ThirtyMinutes, offset);
+        assert_eq!(end.to_rfc3339(), "2025-03-08T10:00:00+05:45");
+        let end = chunk_end(start, Resolution::Raw, offset);
+        assert_eq!(end.to_rfc3339(), "2025-03-02T10:17:00+05:45");
+    }
+}
@@ -0,0 +1,658 @@
+use chrono::{DateTime, Duration, FixedOffset};
+
+/// A Flux string literal, escaped so that bucket, measurement and field names can't break out of it
+fn string(value: &str) -> String {
+    let mut literal = String::with_capacity(value.len() + 2);
+    literal.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => literal.push_str("\\\""),
+            '\\' => literal.push_str("\\\\"),
+            '\n' => literal.push_str("\\n"),
+            '\r' => literal.push_str("\\r"),
+            '\t' => literal.push_str("\\t"),
+            // Flux interpolates ${...} inside string literals
+            '$' => literal.push_str("\\$"),
+            c => literal.push(c),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+fn string_array(values: &[&str]) -> String {
+    let values = values.iter().map(|value| string(value)).collect::<Vec<_>>();
+    format!("[{}]", values.join(", "))
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c == '_' || c.is_ascii_alphabetic())
+        && chars.all(|c| c == '_' || c.is_ascii_alphanumeric())
+}
+
+/// A key in a record literal
+fn key(name: &str) -> String {
+    if is_identifier(name) {
+        name.to_owned()
+    } else {
+        string(name)
+    }
+}
+
+/// A duration literal in the largest whole unit
+pub(crate) fn duration(value: Duration) -> String {
+    let seconds = value.num_seconds();
+    match seconds {
+        0 => "0s".to_owned(),
+        s if s % 86400 == 0 => format!("{}d", s / 86400),
+        s if s % 3600 == 0 => format!("{}h", s / 3600),
+        s if s % 60 == 0 => format!("{}m", s / 60),
+        s => format!("{s}s"),
+    }
+}
+
+fn time(value: &DateTime<FixedOffset>) -> String {
+    format!("time(v: {})", string(&value.to_rfc3339()))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Operator {
+    Or,
+    And,
+    Equal,
+    Less,
+    LessEqual,
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+impl Operator {
+    fn symbol(&self) -> &'static str {
+        match self {
+            Operator::Or => "or",
+            Operator::And => "and",
+            Operator::Equal => "==",
+            Operator::Less => "<",
+            Operator::LessEqual => "<=",
+            Operator::Add => "+",
+            Operator::Sub => "-",
+            Operator::Mul => "*",
+            Operator::Div => "/",
+        }
+    }
+
+    fn precedence(&self) -> u8 {
+        match self {
+            Operator::Or => 1,
+            Operator::And => 2,
+            Operator::Equal | Operator::Less | Operator::LessEqual => 4,
+            Operator::Add | Operator::Sub => 5,
+            Operator::Mul | Operator::Div => 6,
+        }
+    }
+}
+
+// not and exists bind more loosely than comparisons and arithmetic
+const UNARY_PRECEDENCE: u8 = 3;
+const ATOM_PRECEDENCE: u8 = 7;
+
+/// An expression on the row `r`, rendered with only the parentheses Flux needs
+#[derive(Debug, Clone)]
+pub(crate) enum Expr {
+    Column(String),
+    Name(String),
+    Float(f64),
+    Bool(bool),
+    String(String),
+    Exists(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(Box<Expr>, Operator, Box<Expr>),
+    Call(&'static str, Vec<(&'static str, Expr)>),
+    If(Box<Expr>, Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    /// A column of the row `r`
+    pub(crate) fn column(name: &str) -> Self {
+        Expr::Column(name.to_owned())
+    }
+
+    /// A variable of a map block or a package member such as math.pi
+    pub(crate) fn name(name: &str) -> Self {
+        debug_assert!(
+            name.split('.').all(is_identifier),
+            "invalid Flux identifier {name}"
+        );
+        Expr::Name(name.to_owned())
+    }
+
+    /// A float literal, which Flux only has for finite values
+    pub(crate) fn float(value: f64) -> Self {
+        assert!(value.is_finite(), "no Flux literal for {value}");
+        Expr::Float(value)
+    }
+
+    pub(crate) fn boolean(value: bool) -> Self {
+        Expr::Bool(value)
+    }
+
+    pub(crate) fn string(value: &str) -> Self {
+        Expr::String(value.to_owned())
+    }
+
+    pub(crate) fn call(
+        function: &'static str,
+        arguments: impl IntoIterator<Item = (&'static str, Expr)>,
+    ) -> Self {
+        Expr::Call(function, arguments.into_iter().collect())
+    }
+
+    pub(crate) fn if_else(condition: Expr, then: Expr, otherwise: Expr) -> Self {
+        Expr::If(Box::new(condition), Box::new(then), Box::new(otherwise))
+    }
+
+    pub(crate) fn exists(self) -> Self {
+        Expr::Exists(Box::new(self))
+    }
+
+    fn binary(self, operator: Operator, other: Expr) -> Self {
+        Expr::Binary(Box::new(self), operator, Box::new(other))
+    }
+
+    pub(crate) fn and(self, other: Expr) -> Self {
+        self.binary(Operator::And, other)
+    }
+
+    pub(crate) fn or(self, other: Expr) -> Self {
+        self.binary(Operator::Or, other)
+    }
+
+    pub(crate) fn equals(self, other: Expr) -> Self {
+        self.binary(Operator::Equal, other)
+    }
+
+    pub(crate) fn less_than(self, other: Expr) -> Self {
+        self.binary(Operator::Less, other)
+    }
+
+    pub(crate) fn less_or_equal(self, other: Expr) -> Self {
+        self.binary(Operator::LessEqual, other)
+    }
+
+    fn precedence(&self) -> u8 {
+        match self {
+            Expr::If(..) => 0,
+            Expr::Exists(_) | Expr::Not(_) => UNARY_PRECEDENCE,
+            Expr::Binary(_, operator, _) => operator.precedence(),
+            _ => ATOM_PRECEDENCE,
+        }
+    }
+
+    /// Renders the expression, parenthesised if it binds more loosely than its context
+    fn render_within(&self, precedence: u8) -> String {
+        let flux = self.render();
+        match self.precedence() < precedence {
+            true => format!("({flux})"),
+            false => flux,
+        }
+    }
+
+    pub(crate) fn render(&self) -> String {
+        match self {
+            // Member syntax only where the name is a valid identifier
+            Expr::Column(name) if is_identifier(name) => format!("r.{name}"),
+            Expr::Column(name) => format!("r[{}]", string(name)),
+            Expr::Name(name) => name.clone(),
+            // Display never uses an exponent, which Flux doesn't accept, but drops the point
+            Expr::Float(value) => match value.to_string() {
+                literal if literal.contains('.') => literal,
+                literal => format!("{literal}.0"),
+            },
+            Expr::Bool(value) => value.to_string(),
+            Expr::String(value) => string(value),
+            Expr::Exists(operand) => format!("exists {}", operand.render_within(ATOM_PRECEDENCE)),
+            Expr::Not(operand) => format!("not {}", operand.render_within(UNARY_PRECEDENCE)),
+            // Operators are left associative, so an equal right operand is parenthesised
+            Expr::Binary(left, operator, right) => format!(
+                "{} {} {}",
+                left.render_within(operator.precedence()),
+                operator.symbol(),
+                right.render_within(operator.precedence() + 1)
+            ),
+            Expr::Call(function, arguments) => {
+                let arguments = arguments
+                    .iter()
+                    .map(|(name, value)| format!("{name}: {}", value.render()))
+                    .collect::<Vec<_>>();
+                format!("{function}({})", arguments.join(", "))
+            }
+            Expr::If(condition, then, otherwise) => format!(
+                "if {} then {} else {}",
+                condition.render(),
+                then.render(),
+                otherwise.render()
+            ),
+        }
+    }
+}
+
+impl std::ops::Not for Expr {
+    type Output = Expr;
+
+    fn not(self) -> Expr {
+        Expr::Not(Box::new(self))
+    }
+}
+
+impl std::ops::Add for Expr {
+    type Output = Expr;
+
+    fn add(self, other: Expr) -> Expr {
+        self.binary(Operator::Add, other)
+    }
+}
+
+impl std::ops::Sub for Expr {
+    type Output = Expr;
+
+    fn sub(self, other: Expr) -> Expr {
+        self.binary(Operator::Sub, other)
+    }
+}
+
+impl std::ops::Mul for Expr {
+    type Output = Expr;
+
+    fn mul(self, other: Expr) -> Expr {
+        self.binary(Operator::Mul, other)
+    }
+}
+
+impl std::ops::Div for Expr {
+    type Output = Expr;
+
+    fn div(self, other: Expr) -> Expr {
+        self.binary(Operator::Div, other)
+    }
+}
+
+/// The fields of a record literal extending `r`, indented one level
+fn with_columns(columns: &[(&str, Expr)]) -> String {
+    let columns = columns
+        .iter()
+        .map(|(name, expression)| format!("    {}: {},\n", key(name), expression.render()))
+        .collect::<String>();
+    format!("{{r with\n{columns}}}")
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Aggregate {
+    Mean,
+    Max,
+    Min,
+    Sum,
+    Count,
+}
+
+impl Aggregate {
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Aggregate::Mean => "mean",
+            Aggregate::Max => "max",
+            Aggregate::Min => "min",
+            Aggregate::Sum => "sum",
+            Aggregate::Count => "count",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Source {
+    From(String),
+    Table(String),
+    Union(Vec<Pipeline>),
+}
+
+/// A table source followed by the functions its rows are piped through
+#[derive(Debug, Clone)]
+pub(crate) struct Pipeline {
+    source: Source,
+    stages: Vec<String>,
+}
+
+impl Pipeline {
+    fn new(source: Source) -> Self {
+        Self {
+            source,
+            stages: Vec::new(),
+        }
+    }
+
+    pub(crate) fn from(bucket: &str) -> Self {
+        Self::new(Source::From(bucket.to_owned()))
+    }
+
+    pub(crate) fn union(tables: impl IntoIterator<Item = Pipeline>) -> Self {
+        Self::new(Source::Union(tables.into_iter().collect()))
+    }
+
+    fn stage(mut self, stage: String) -> Self {
+        self.stages.push(stage);
+        self
+    }
+
+    pub(crate) fn range(self, start: &DateTime<FixedOffset>, stop: &DateTime<FixedOffset>) -> Self {
+        let stage = format!("range(start: {}, stop: {})", time(start), time(stop));
+        self.stage(stage)
+    }
+
+    /// Rows from the given duration before now
+    pub(crate) fn range_since(self, since: Duration) -> Self {
+        let stage = format!("range(start: -{})", duration(since));
+        self.stage(stage)
+    }
+
+    pub(crate) fn filter_measurement(self, measurement: &str) -> Self {
+        self.filter(Expr::column("_measurement").equals(Expr::string(measurement)))
+    }
+
+    pub(crate) fn filter_fields(self, fields: &[&str]) -> Self {
+        let predicate = fields
+            .iter()
+            .map(|field| Expr::column("_field").equals(Expr::string(field)))
+            .reduce(Expr::or)
+            .unwrap_or(Expr::boolean(false));
+        self.filter(predicate)
+    }
+
+    /// Keeps rows matching a predicate
+    pub(crate) fn filter(self, predicate: Expr) -> Self {
+        self.stage(format!("filter(fn: (r) => {})", predicate.render()))
+    }
+
+    /// Windows shifted from the epoch by the given offset, each labelled with its end
+    pub(crate) fn aggregate_window(
+        self,
+        every: Duration,
+        offset: Duration,
+        aggregate: Aggregate,
+    ) -> Self {
+        let offset = match offset.is_zero() {
+            true => String::new(),
+            false => format!("offset: {}, ", duration(offset)),
+        };
+        let stage = format!(
+            "aggregateWindow(every: {}, {offset}fn: {}, createEmpty: false)",
+            duration(every),
+            aggregate.name()
+        );
+        self.stage(stage)
+    }
+
+    /// As aggregate_window, with each window labelled with its start
+    pub(crate) fn aggregate_window_from(
+        self,
+        every: Duration,
+        offset: Duration,
+        aggregate: Aggregate,
+    ) -> Self {
+        let stage = format!(
+            "aggregateWindow(every: {}, offset: {}, fn: {}, createEmpty: false, timeSrc: \"_start\")",
+            duration(every),
+            duration(offset),
+            aggregate.name()
+        );
+        self.stage(stage)
+    }
+
+    /// One row per time with a column for each field
+    pub(crate) fn pivot(self) -> Self {
+        self.stage(
+            "pivot(rowKey: [\"_time\"], columnKey: [\"_field\"], valueColumn: \"_value\")"
+                .to_owned(),
+        )
+    }
+
+    /// The reverse of pivot, needing the experimental package
+    pub(crate) fn unpivot(self) -> Self {
+        self.stage("experimental.unpivot()".to_owned())
+    }
+
+    /// Sets each column to an expression, keeping the others
+    pub(crate) fn map_with(self, columns: &[(&str, Expr)]) -> Self {
+        self.stage(format!("map(fn: (r) => ({}))", with_columns(columns)))
+    }
+
+    /// As map_with, with the columns able to use variables assigned in order beforehand
+    pub(crate) fn map_block(self, variables: &[(&str, Expr)], columns: &[(&str, Expr)]) -> Self {
+        let mut body = variables
+            .iter()
+            .map(|(name, expression)| {
+                debug_assert!(is_identifier(name), "invalid Flux identifier {name}");
+                format!("{name} = {}\n", expression.render())
+            })
+            .collect::<String>();
+        body.push_str(&format!("return {}", with_columns(columns)));
+        let body = body
+            .lines()
+            .map(|line| format!("    {line}\n"))
+            .collect::<String>();
+        self.stage(format!("map(fn: (r) => {{\n{body}}})"))
+    }
+
+    pub(crate) fn drop(self, columns: &[&str]) -> Self {
+        self.stage(format!("drop(columns: {})", string_array(columns)))
+    }
+
+    pub(crate) fn keep(self, columns: &[&str]) -> Self {
+        self.stage(format!("keep(columns: {})", string_array(columns)))
+    }
+
+    pub(crate) fn group(self, columns: &[&str]) -> Self {
+        self.stage(format!("group(columns: {})", string_array(columns)))
+    }
+
+    /// Merges every table into one
+    pub(crate) fn group_all(self) -> Self {
+        self.stage("group()".to_owned())
+    }
+
+    pub(crate) fn sort(self, columns: &[&str]) -> Self {
+        self.stage(format!("sort(columns: {})", string_array(columns)))
+    }
+
+    pub(crate) fn last(self, column: &str) -> Self {
+        self.stage(format!("last(column: {})", string(column)))
+    }
+
+    pub(crate) fn unique(self, column: &str) -> Self {
+        self.stage(format!("unique(column: {})", string(column)))
+    }
+
+    pub(crate) fn time_shift(self, shift: Duration) -> Self {
+        let stage = format!(
+            "timeShift(duration: {}, columns: [\"_time\"])",
+            duration(shift)
+        );
+        self.stage(stage)
+    }
+
+    /// Converts _value to a float, as counts are integers
+    pub(crate) fn float_values(self) -> Self {
+        self.stage("toFloat()".to_owned())
+    }
+
+    fn render(&self, indent: &str) -> String {
+        let mut flux = match &self.source {
+            Source::From(bucket) => format!("from(bucket: {})", string(bucket)),
+            Source::Table(name) => name.clone(),
+            Source::Union(tables) if tables.iter().all(|table| table.stages.is_empty()) => {
+                let tables = tables
+                    .iter()
+                    .map(|table| table.render(indent))
+                    .collect::<Vec<_>>();
+                format!("union(tables: [{}])", tables.join(", "))
+            }
+            Source::Union(tables) => {
+                let nested = format!("{indent}    ");
+                let tables = tables
+                    .iter()
+                    .map(|table| format!("{nested}{},\n", table.render(&nested)))
+                    .collect::<String>();
+                format!("union(tables: [\n{tables}{indent}])")
+            }
+        };
+        for stage in &self.stages {
+            flux.push('\n');
+            flux.push_str(indent);
+            flux.push_str("|> ");
+            flux.push_str(&stage.replace('\n', &format!("\n{indent}")));
+        }
+        flux
+    }
+}
+
+/// A Flux script of named pipelines followed by the one that produces the result
+#[derive(Debug, Default)]
+pub(crate) struct Query {
+    imports: Vec<&'static str>,
+    statements: Vec<String>,
+}
+
+impl Query {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn import(mut self, package: &'static str) -> Self {
+        self.imports.push(package);
+        self
+    }
+
+    /// Binds a pipeline to a name, returning a pipeline that starts from it
+    pub(crate) fn assign(&mut self, name: &str, pipeline: Pipeline) -> Pipeline {
+        debug_assert!(is_identifier(name), "invalid Flux identifier {name}");
+        self.statements
+            .push(format!("{name} = {}", pipeline.render("")));
+        Pipeline::new(Source::Table(name.to_owned()))
+    }
+
+    pub(crate) fn result(mut self, pipeline: Pipeline) -> String {
+        self.statements.push(pipeline.render(""));
+        let mut flux = self
+            .imports
+            .iter()
+            .map(|package| format!("import {}\n", string(package)))
+            .collect::<String>();
+        if !flux.is_empty() {
+            flux.push('\n');
+        }
+        flux.push_str(&self.statements.join("\n\n"));
+        flux.push('\n');
+        flux
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strings_are_escaped() {
+        assert_eq!(string("plain"), "\"plain\"");
+        assert_eq!(string("a\"b\\c"), "\"a\\\"b\\\\c\"");
+        assert_eq!(string("line\nbreak\ttab"), "\"line\\nbreak\\ttab\"");
+        assert_eq!(string("${secret}"), "\"\\${secret}\"");
+    }
+
+    #[test]
+    fn columns_use_member_syntax_for_identifiers() {
+        assert_eq!(Expr::column("tempc").render(), "r.tempc");
+        assert_eq!(Expr::column("_time").render(), "r._time");
+        assert_eq!(Expr::column("wind speed").render(), "r[\"wind speed\"]");
+        assert_eq!(Expr::column("2m_temp").render(), "r[\"2m_temp\"]");
+    }
+
+    #[test]
+    fn expressions_are_parenthesised_only_where_needed() {
+        let (a, b, c) = (Expr::column("a"), Expr::column("b"), Expr::column("c"));
+        assert_eq!(
+            (a.clone() + b.clone() * c.clone()).render(),
+            "r.a + r.b * r.c"
+        );
+        assert_eq!(
+            ((a.clone() + b.clone()) * c.clone()).render(),
+            "(r.a + r.b) * r.c"
+        );
+        assert_eq!(
+            (a.clone() - (b.clone() - c.clone())).render(),
+            "r.a - (r.b - r.c)"
+        );
+        assert_eq!(
+            (a.clone() * b.clone() / c.clone()).render(),
+            "r.a * r.b / r.c"
+        );
+        assert_eq!(
+            (!a.clone().exists())
+                .or(b.clone().less_than(Expr::float(0.0)))
+                .render(),
+            "not exists r.a or r.b < 0.0"
+        );
+        assert_eq!(
+            a.clone()
+                .or(b.clone())
+                .and(!c.clone().equals(Expr::string("x")))
+                .render(),
+            "(r.a or r.b) and not r.c == \"x\""
+        );
+        assert_eq!(
+            Expr::if_else(a.clone().exists(), a.clone(), Expr::float(-1.0)).render(),
+            "if exists r.a then r.a else -1.0"
+        );
+        assert_eq!(
+            (Expr::if_else(a.clone().exists(), a, Expr::float(0.0)) + b).render(),
+            "(if exists r.a then r.a else 0.0) + r.b"
+        );
+        assert_eq!(
+            Expr::call("math.mMin", [("x", Expr::float(1.0)), ("y", c)]).render(),
+            "math.mMin(x: 1.0, y: r.c)"
+        );
+    }
+
+    #[test]
+    fn predicates_escape_their_values() {
+        let flux = Pipeline::from("weather")
+            .filter_measurement("obs\" or true or \"")
+            .render("");
+        assert_eq!(
+            flux,
+            "from(bucket: \"weather\")\n|> filter(fn: (r) => r._measurement == \"obs\\\" or true or \\\"\")"
+        );
+    }
+
+    #[test]
+    fn floats_are_plain_decimals() {
+        assert_eq!(Expr::float(1.0).render(), "1.0");
+        assert_eq!(Expr::float(-0.25).render(), "-0.25");
+        assert_eq!(Expr::float(1e-7).render(), "0.0000001");
+        assert_eq!(Expr::float(1e21).render(), "1000000000000000000000.0");
+    }
+
+    #[test]
+    #[should_panic(expected = "no Flux literal for NaN")]
+    fn floats_must_be_finite() {
+        Expr::float(f64::NAN);
+    }
+
+    #[test]
+    fn durations_use_the_largest_whole_unit() {
+        assert_eq!(duration(Duration::days(1)), "1d");
+        assert_eq!(duration(Duration::hours(-13)), "-13h");
+        assert_eq!(duration(Duration::minutes(90)), "90m");
+        assert_eq!(duration(Duration::seconds(45)), "45s");
+        assert_eq!(duration(Duration::zero()), "0s");
+    }
+}
@@ -0,0 +1,486 @@
+mod builder;
+
+use crate::ApiError;
+use crate::pressure::sea_level_pressure_stats;
+use crate::rain::RainCounters;
+use crate::resolution::Resolution;
+use crate::stats::{DaySummary, FieldStats};
+use crate::store::LATEST_WITHIN;
+use crate::types::HourRecordFlux;
+use builder::{Aggregate, Expr, Pipeline, Query};
+use chrono::{DateTime, Duration, FixedOffset};
+use influxdb2::Client;
+use influxdb2::api::query::FluxRecord;
+use influxdb2::models::Query as InfluxQuery;
+use influxdb2_structmap::value::Value;
+
+// Fields averaged over each window
+const MEAN_FIELDS: [&str; 9] = [
+    "tempc",
+    "tempinc",
+    "humidity",
+    "humidityin",
+    "windspeedkph",
+    "rainratemm",
+    "solarradiation",
+    "baromrelhpa",
+    "baromabshpa",
+];
+
+// The highest reading of each rain counter, as the mean of a counter that reset mid-window is
+// meaningless, and of gusts and UV, which are peaks
+const MAX_FIELDS: [&str; 5] = [
+    "totalrainmm",
+    "dailyrainmm",
+    "eventrainmm",
+    "windgustkph",
+    "uv",
+];
+
+// Averaged as a vector, weighted by windspeedkph
+const WIND_DIRECTION_FIELD: &str = "winddir";
+
+// Converts the mean wind components back to a direction, with the steadiness being how much of the
+// mean speed survives the vector average
+fn wind_from_components(pipeline: Pipeline) -> Pipeline {
+    let (u, v) = (Expr::column("windu"), Expr::column("windv"));
+    let speed = Expr::column("windspeedkph");
+    let (calm, direction) = (Expr::name("calm"), Expr::name("direction"));
+    let zero = || Expr::float(0.0);
+
+    let is_calm = (!u.clone().exists())
+        .or(!v.clone().exists())
+        .or(!speed.clone().exists())
+        .or(speed.clone().less_or_equal(zero()));
+    let atan2 = Expr::call("math.atan2", [("y", u.clone()), ("x", v.clone())]);
+    let mean_direction = Expr::if_else(
+        calm.clone(),
+        zero(),
+        atan2 * Expr::float(180.0) / Expr::name("math.pi"),
+    );
+    let magnitude = Expr::call("math.sqrt", [("x", u.clone() * u + v.clone() * v)]);
+    let steadiness = Expr::call(
+        "math.mMin",
+        [("x", Expr::float(1.0)), ("y", magnitude / speed)],
+    );
+    pipeline.map_block(
+        &[("calm", is_calm), ("direction", mean_direction)],
+        &[
+            (
+                WIND_DIRECTION_FIELD,
+                Expr::if_else(
+                    direction.clone().less_than(zero()),
+                    direction.clone() + Expr::float(360.0),
+                    direction,
+                ),
+            ),
+            ("windsteadiness", Expr::if_else(calm, zero(), steadiness)),
+        ],
+    )
+}
+
+fn observation_fields() -> Vec<&'static str> {
+    MEAN_FIELDS
+        .into_iter()
+        .chain(MAX_FIELDS)
+        .chain([WIND_DIRECTION_FIELD])
+        .collect()
+}
+
+// Every observation in the source as a row, with a column for each field it reported
+fn raw_observations(source: Pipeline) -> Pipeline {
+    observation_rows(source.filter_fields(&observation_fields()))
+}
+
+// Field values pivoted into a row for each observation time
+fn observation_rows(fields: Pipeline) -> Pipeline {
+    fields
+        .pivot()
+        .map_with(&[("windsteadiness", Expr::float(1.0))])
+}
+
+pub(crate) fn build_range_flux(
+    bucket: &str,
+    measurement: &str,
+    start: &DateTime<FixedOffset>,
+    end: &DateTime<FixedOffset>,
+    resolution: Resolution,
+    utc_offset: Duration,
+) -> String {
+    let data = Pipeline::from(bucket)
+        .range(start, end)
+        .filter_measurement(measurement);
+    let (Some(every), Some(offset)) = (
+        resolution.seconds().map(Duration::seconds),
+        resolution.window_offset(utc_offset),
+    ) else {
+        return Query::new().result(raw_observations(data).sort(&["_time"]));
+    };
+
+    // Wind direction is averaged as a vector, so its speed weighted components are aggregated
+    // alongside the other fields and converted back to a direction after the pivot
+    let mut query = Query::new().import("experimental").import("math");
+    let data = query.assign("data", data);
+    let fields = query.assign("fields", data.clone().filter_fields(&MEAN_FIELDS));
+    let maxima = query.assign(
+        "maxima",
+        data.clone()
+            .filter_fields(&MAX_FIELDS)
+            .aggregate_window(every, offset, Aggregate::Max),
+    );
+    let speed = Expr::column("windspeedkph");
+    let direction = Expr::column(WIND_DIRECTION_FIELD);
+    let radians = direction.clone() * Expr::name("math.pi") / Expr::float(180.0);
+    let wind = query.assign(
+        "wind",
+        data.filter_fields(&["windspeedkph", WIND_DIRECTION_FIELD])
+            .pivot()
+            .filter(speed.clone().exists().and(direction.exists()))
+            .map_with(&[
+                (
+                    "windu",
+                    speed.clone() * Expr::call("math.sin", [("x", radians.clone())]),
+                ),
+                ("windv", speed * Expr::call("math.cos", [("x", radians)])),
+            ])
+            .drop(&["windspeedkph", WIND_DIRECTION_FIELD])
+            .unpivot(),
+    );
+    let means = query.assign(
+        "means",
+        Pipeline::union([fields, wind]).aggregate_window(every, offset, Aggregate::Mean),
+    );
+    let combined =
+        wind_from_components(Pipeline::union([means, maxima]).pivot()).drop(&["windu", "windv"]);
+    query.result(combined.sort(&["_time"]))
+}
+
+/// The newest observation. Each field's last value is taken before pivoting, so that only a few
+/// rows are pivoted, and fields whose last value is older than the newest observation are left in
+/// the rows dropped by the final last.
+pub(crate) fn build_latest_flux(bucket: &str, measurement: &str) -> String {
+    let fields = Pipeline::from(bucket)
+        .range_since(LATEST_WITHIN)
+        .filter_measurement(measurement)
+        .filter_fields(&observation_fields())
+        .last("_value");
+    let latest = observation_rows(fields).group_all().last("_time");
+    Query::new().result(latest)
+}
+
+pub(crate) fn build_observation_times_flux(
+    bucket: &str,
+    measurement: &str,
+    start: &DateTime<FixedOffset>,
+    end: &DateTime<FixedOffset>,
+) -> String {
+    let times = Pipeline::from(bucket)
+        .range(start, end)
+        .filter_measurement(measurement)
+        .keep(&["_time"])
+        .group_all()
+        .unique("_time");
+    Query::new().result(times)
+}
+
+/// Reads observation rows, with fields missing from a row read as None
+pub(crate) async fn query_flux(
+    client: &Client,
+    flux: &str,
+) -> Result<Vec<HourRecordFlux>, ApiError> {
+    let records = client
+        .query_raw(Some(InfluxQuery::new(flux.to_owned())))
+        .await?;
+    Ok(records
+        .iter()
+        .filter_map(|record| {
+            let Some(Value::TimeRFC(time)) = record.values.get("_time") else {
+                return None;
+            };
+            let value = |column: &str| record_value(record, column);
+            Some(HourRecordFlux {
+                time: *time,
+                tempc: value("tempc"),
+                tempinc: value("tempinc"),
+                humidity: value("humidity"),
+                humidityin: value("humidityin"),
+                windspeedkph: value("windspeedkph"),
+                windgustkph: value("windgustkph"),
+                winddir: value(WIND_DIRECTION_FIELD),
+                windsteadiness: value("windsteadiness").unwrap_or(0_f64),
+                rainratemm: value("rainratemm"),
+                totalrainmm: value("totalrainmm"),
+                dailyrainmm: value("dailyrainmm"),
+                eventrainmm: value("eventrainmm"),
+                uv: value("uv"),
+                solarradiation: value("solarradiation"),
+                baromrelhpa: value("baromrelhpa"),
+                baromabshpa: value("baromabshpa"),
+            })
+        })
+        .collect())
+}
+
+const RAIN_COUNTER_FIELDS: [&str; 3] = ["totalrainmm", "dailyrainmm", "eventrainmm"];
+
+// Fields summarised for each day by build_day_summaries_flux
+const DAY_SUMMARY_FIELDS: [&str; 9] = [
+    "tempc",
+    "tempinc",
+    "humidity",
+    "humidityin",
+    "uv",
+    "solarradiation",
+    "baromabshpa",
+    "baromrelhpa",
+    "windgustkph",
+];
+
+/// Minimum, maximum, sum and count of each field for each day in the station's timezone, as
+/// columns named like tempc_min. Days are labelled with midnight UTC on their date.
+pub(crate) fn build_day_summaries_flux(
+    bucket: &str,
+    measurement: &str,
+    start: &DateTime<FixedOffset>,
+    end: &DateTime<FixedOffset>,
+    utc_offset: Duration,
+) -> String {
+    let day = Duration::days(1);
+    let day_offset = Duration::seconds((-utc_offset).num_seconds().rem_euclid(86400));
+
+    let mut query = Query::new();
+    let data = query.assign(
+        "data",
+        Pipeline::from(bucket)
+            .range(start, end)
+            .filter_measurement(measurement),
+    );
+    let fields = query.assign(
+        "fields",
+        data.filter_fields(&DAY_SUMMARY_FIELDS).group(&["_field"]),
+    );
+    let stats = [
+        Aggregate::Min,
+        Aggregate::Max,
+        Aggregate::Sum,
+        Aggregate::Count,
+    ]
+    .map(|aggregate| {
+        let suffix = Expr::string(&format!("_{}", aggregate.name()));
+        fields
+            .clone()
+            .aggregate_window_from(day, day_offset, aggregate)
+            .float_values()
+            .map_with(&[("_field", Expr::column("_field") + suffix)])
+    });
+    query.result(
+        Pipeline::union(stats)
+            .time_shift(utc_offset)
+            .keep(&["_time", "_field", "_value"])
+            .group_all()
+            .pivot()
+            .sort(&["_time"]),
+    )
+}
+
+/// The station's rain counters at each observation, for totalling rain the same way as the
+/// other stores
+pub(crate) fn build_rain_counters_flux(
+    bucket: &str,
+    measurement: &str,
+    start: &DateTime<FixedOffset>,
+    end: &DateTime<FixedOffset>,
+) -> String {
+    let mut columns = vec!["_time"];
+    columns.extend(RAIN_COUNTER_FIELDS);
+    let counters = Pipeline::from(bucket)
+        .range(start, end)
+        .filter_measurement(measurement)
+        .filter_fields(&RAIN_COUNTER_FIELDS)
+        .pivot()
+        .keep(&columns)
+        .group_all()
+        .sort(&["_time"]);
+    Query::new().result(counters)
+}
+
+fn record_value(record: &FluxRecord, column: &str) -> Option<f64> {
+    record.values.get(column).and_then(|value| value.f64())
+}
+
+fn record_field_stats(record: &FluxRecord, field: &str) -> FieldStats {
+    let count = record_value(record, &format!("{field}_count")).unwrap_or(0_f64) as u64;
+    if count == 0 {
+        return FieldStats::default();
+    }
+    FieldStats {
+        min: record_value(record, &format!("{field}_min")).unwrap_or(f64::MAX),
+        max: record_value(record, &format!("{field}_max")).unwrap_or(f64::MIN),
+        sum: record_value(record, &format!("{field}_sum")).unwrap_or(0_f64),
+        count,
+    }
+}
+
+pub(crate) async fn query_flux_day_summaries(
+    client: &Client,
+    flux: &str,
+    elevation: Option<f64>,
+) -> Result<Vec<DaySummary>, ApiError> {
+    let records = client
+        .query_raw(Some(InfluxQuery::new(flux.to_owned())))
+        .await?;
+    Ok(records
+        .iter()
+        .filter_map(|record| {
+            let Some(Value::TimeRFC(time)) = record.values.get("_time") else {
+                return None;
+            };
+            let mut day = DaySummary::new(time.date_naive());
+            day.tempc = record_field_stats(record, "tempc");
+            day.tempinc = record_field_stats(record, "tempinc");
+            day.humidity = record_field_stats(record, "humidity");
+            day.humidityin = record_field_stats(record, "humidityin");
+            day.uv = record_field_stats(record, "uv");
+            day.solarradiation = record_field_stats(record, "solarradiation");
+            day.windgustkph = record_field_stats(record, "windgustkph");
+            day.baromslhpa = sea_level_pressure_stats(
+                record_field_stats(record, "baromabshpa"),
+                record_field_stats(record, "baromrelhpa"),
+                elevation,
+            );
+            Some(day)
+        })
+        .collect())
+}
+
+pub(crate) async fn query_flux_rain_counters(
+    client: &Client,
+    flux: &str,
+) -> Result<Vec<RainCounters>, ApiError> {
+    let records = client
+        .query_raw(Some(InfluxQuery::new(flux.to_owned())))
+        .await?;
+    Ok(records
+        .iter()
+        .filter_map(|record| {
+            let Some(Value::TimeRFC(time)) = record.values.get("_time") else {
+                return None;
+            };
+            Some(RainCounters {
+                time: *time,
+                totalrainmm: record_value(record, "totalrainmm"),
+                dailyrainmm: record_value(record, "dailyrainmm"),
+                eventrainmm: record_value(record, "eventrainmm"),
+            })
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    // Compares against the file in src/flux/golden, or rewrites it when UPDATE_GOLDEN is set
+    fn assert_golden(name: &str, flux: &str) {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("src/flux/golden")
+            .join(format!("{name}.flux"));
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            std::fs::write(&path, flux).unwrap();
+            return;
+        }
+        let expected = std::fs::read_to_string(&path)
+            .unwrap_or_else(|err| panic!("reading {}: {err}", path.display()));
+        assert_eq!(flux, expected, "{} differs", path.display());
+    }
+
+    fn time(value: &str) -> DateTime<FixedOffset> {
+        DateTime::parse_from_rfc3339(value).unwrap()
+    }
+
+    #[test]
+    fn raw_range() {
+        let flux = build_range_flux(
+            "weather",
+            "observations",
+            &time("2025-01-01T00:00:00+13:00"),
+            &time("2025-01-02T00:00:00+13:00"),
+            Resolution::Raw,
+            Duration::hours(13),
+        );
+        assert_golden("range_raw", &flux);
+    }
+
+    #[test]
+    fn aggregated_range() {
+        let flux = build_range_flux(
+            "weather",
+            "observations",
+            &time("2025-01-01T00:00:00+13:00"),
+            &time("2025-01-02T00:00:00+13:00"),
+            Resolution::FiveMinutes,
+            Duration::hours(13),
+        );
+        assert_golden("range_5m", &flux);
+    }
+
+    #[test]
+    fn daily_range_follows_local_days() {
+        let flux = build_range_flux(
+            "weather",
+            "observations",
+            &time("2025-01-01T00:00:00+13:00"),
+            &time("2025-04-01T00:00:00+13:00"),
+            Resolution::Day,
+            Duration::hours(13),
+        );
+        assert_golden("range_1d", &flux);
+    }
+
+    #[test]
+    fn latest() {
+        assert_golden("latest", &build_latest_flux("weather", "observations"));
+    }
+
+    #[test]
+    fn observation_times() {
+        let flux = build_observation_times_flux(
+            "weather",
+            "observations",
+            &time("2025-01-01T00:00:00+13:00"),
+            &time("2025-01-04T00:00:00+13:00"),
+        );
+        assert_golden("observation_times", &flux);
+    }
+
+    #[test]
+    fn day_summaries() {
+        let flux = build_day_summaries_flux(
+            "weather",
+            "observations",
+            &time("2025-01-01T00:00:00+13:00"),
+            &time("2025-02-01T00:00:00+13:00"),
+            Duration::hours(13),
+        );
+        assert_golden("day_summaries", &flux);
+    }
+
+    #[test]
+    fn rain_counters() {
+        let flux = build_rain_counters_flux(
+            "weather",
+            "observations",
+            &time("2025-01-01T00:00:00+13:00"),
+            &time("2025-02-01T00:00:00+13:00"),
+        );
+        assert_golden("rain_counters", &flux);
+    }
+
+    #[test]
+    fn names_are_escaped() {
+        let flux = build_latest_flux("bucket\") |> drop(", "weather ${station}");
+        assert!(flux.starts_with("from(bucket: \"bucket\\\") |> drop(\")\n"));
+        assert!(flux.contains("r._measurement == \"weather \\${station}\""));
+    }
+}
@@ -0,0 +1,151 @@
+use crate::pressure::PressureTendency;
+use crate::wind::sector;
+use crate::{ApiError, ServerState, pressure_trend};
+use axum::Json;
+use axum::extract::State;
+use chrono::{Datelike, Local};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+// Pressure range covered by the Zambretti tables
+const BAROMETER_TOP: f64 = 1050_f64;
+const BAROMETER_BOTTOM: f64 = 950_f64;
+const BAROMETER_RANGE: f64 = BAROMETER_TOP - BAROMETER_BOTTOM;
+
+// Adjustment to the pressure for each 16-point wind direction, as a percentage of the range, for
+// the northern hemisphere. Southerly winds bring worse weather than northerly ones.
+const WIND_ADJUSTMENTS: [f64; 16] = [
+    6_f64, 5_f64, 5_f64, 2_f64, -0.5, -2_f64, -5_f64, -8.5, -12_f64, -10_f64, -6_f64, -4.5, -3_f64,
+    -0.5, 1.5, 3_f64,
+];
+
+// Adjustment to rising pressure in summer and falling pressure in winter, as a percentage of the
+// range
+const SEASON_ADJUSTMENT: f64 = 7_f64;
+
+// Forecast for each of the 22 pressure bands, from lowest to highest pressure
+const RISING_FORECASTS: [usize; 22] = [
+    25, 25, 25, 24, 24, 19, 16, 12, 11, 9, 8, 6, 5, 2, 1, 1, 0, 0, 0, 0, 0, 0,
+];
+const STEADY_FORECASTS: [usize; 22] = [
+    25, 25, 25, 25, 25, 25, 23, 23, 22, 18, 15, 13, 10, 4, 1, 1, 0, 0, 0, 0, 0, 0,
+];
+const FALLING_FORECASTS: [usize; 22] = [
+    25, 25, 25, 25, 25, 25, 25, 25, 23, 23, 21, 20, 17, 14, 7, 3, 1, 1, 1, 0, 0, 0,
+];
+
+const FORECASTS: [(&str, &str); 26] = [
+    ("Settled fine", "sunny"),
+    ("Fine weather", "sunny"),
+    ("Becoming fine", "sunny"),
+    ("Fine, becoming less settled", "partly_cloudy"),
+    ("Fine, possible showers", "showers"),
+    ("Fairly fine, improving", "partly_cloudy"),
+    ("Fairly fine, possible showers early", "showers"),
+    ("Fairly fine, showery later", "showers"),
+    ("Showery early, improving", "showers"),
+    ("Changeable, mending", "partly_cloudy"),
+    ("Fairly fine, showers likely", "showers"),
+    ("Rather unsettled, clearing later", "cloudy"),
+    ("Unsettled, probably improving", "cloudy"),
+    ("Showery, bright intervals", "showers"),
+    ("Showery, becoming less settled", "showers"),
+    ("Changeable, some rain", "rain"),
+    ("Unsettled, short fine intervals", "cloudy"),
+    ("Unsettled, rain later", "rain"),
+    ("Unsettled, some rain", "rain"),
+    ("Mostly very unsettled", "rain"),
+    ("Occasional rain, worsening", "rain"),
+    ("Rain at times, very unsettled", "rain"),
+    ("Rain at frequent intervals", "rain"),
+    ("Rain, very unsettled", "rain"),
+    ("Stormy, may improve", "storm"),
+    ("Stormy, much rain", "storm"),
+];
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct Forecast {
+    /// Zambretti letter, from A (settled fine) to Z (stormy, much rain)
+    pub(crate) letter: String,
+    pub(crate) text: String,
+    pub(crate) icon: String,
+    pub(crate) baromslhpa: f64,
+    pub(crate) pressuretendency: PressureTendency,
+}
+
+/// Zambretti forecast for the next few hours from the mean sea-level pressure, its tendency over
+/// the last three hours and the wind direction (None when calm)
+pub(crate) fn zambretti(
+    baromslhpa: f64,
+    tendency: PressureTendency,
+    winddir: Option<f64>,
+    latitude: f64,
+    month: u32,
+) -> Forecast {
+    let southern = latitude < 0_f64;
+    let mut pressure = baromslhpa;
+
+    if let Some(winddir) = winddir {
+        // Winds from the pole are the fair weather winds in either hemisphere
+        let winddir = if southern { winddir + 180_f64 } else { winddir };
+        pressure += WIND_ADJUSTMENTS[sector(winddir)] / 100_f64 * BAROMETER_RANGE;
+    }
+
+    let summer = if southern {
+        !(4..=9).contains(&month)
+    } else {
+        (4..=9).contains(&month)
+    };
+    let (forecasts, season_adjustment) = match tendency {
+        PressureTendency::RisingRapidly | PressureTendency::Rising => (
+            &RISING_FORECASTS,
+            if summer { SEASON_ADJUSTMENT } else { 0_f64 },
+        ),
+        PressureTendency::Steady => (&STEADY_FORECASTS, 0_f64),
+        PressureTendency::Falling | PressureTendency::FallingRapidly => (
+            &FALLING_FORECASTS,
+            if summer { 0_f64 } else { -SEASON_ADJUSTMENT },
+        ),
+    };
+    pressure += season_adjustment / 100_f64 * BAROMETER_RANGE;
+
+    let band_width = BAROMETER_RANGE / forecasts.len() as f64;
+    let band = ((pressure - BAROMETER_BOTTOM) / band_width)
+        .floor()
+        .clamp(0_f64, (forecasts.len() - 1) as f64) as usize;
+    let index = forecasts[band];
+    let (text, icon) = FORECASTS[index];
+
+    Forecast {
+        letter: ((b'A' + index as u8) as char).to_string(),
+        text: text.to_string(),
+        icon: icon.to_string(),
+        baromslhpa,
+        pressuretendency: tendency,
+    }
+}
+
+pub(crate) async fn forecast(
+    State(state): State<Arc<ServerState>>,
+) -> Result<Json<Forecast>, ApiError> {
+    let Some(latest) = state.store.latest().await? else {
+        return Err(ApiError::Unavailable("No recent data received".to_string()));
+    };
+    let (baromslhpa, change) = pressure_trend(&state, &latest).await?;
+    let (Some(baromslhpa), Some(change)) = (baromslhpa, change) else {
+        return Err(ApiError::Unavailable(
+            "Not enough pressure data to forecast".to_string(),
+        ));
+    };
+
+    Ok(Json(zambretti(
+        baromslhpa,
+        PressureTendency::from_change(change),
+        latest
+            .windspeedkph
+            .filter(|speed| *speed > 0_f64)
+            .and(latest.winddir),
+        state.coordinates.lat(),
+        Local::now().month(),
+    )))
+}
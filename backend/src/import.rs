@@ -0,0 +1,570 @@
+use crate::ApiError;
+use crate::cache::invalidate_saved;
+use crate::ingest::sanitise;
+use crate::rollup::mark_stale;
+use crate::store::WeatherStore;
+use crate::types::Observation;
+use crate::units::{fahrenheit_to_celsius, inches_to_mm, inhg_to_hpa, mph_to_kph};
+use chrono::{DateTime, Days, FixedOffset, Local, NaiveDateTime, TimeZone};
+use clap::{Args, ValueEnum};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+const TIME_FORMATS: [&str; 8] = [
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%d %H:%M",
+    "%Y/%m/%d %H:%M:%S",
+    "%Y/%m/%d %H:%M",
+    "%m/%d/%y %I:%M %p",
+    "%m/%d/%Y %I:%M %p",
+    "%m/%d/%y %H:%M",
+    "%m/%d/%Y %H:%M",
+];
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub(crate) enum CsvLayout {
+    /// Detect the layout from the header row
+    Auto,
+    /// Ecowitt cloud (ecowitt.net) export
+    Ecowitt,
+    /// WeatherLink (weatherlink.com) export
+    Weatherlink,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub(crate) enum RainMode {
+    /// Use the default for the layout
+    Auto,
+    /// The rain column is a running total that only resets occasionally
+    Cumulative,
+    /// The rain column is the amount that fell during each row's interval
+    Interval,
+}
+
+#[derive(Args, Debug)]
+pub(crate) struct ImportArgs {
+    /// CSV files to import
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+
+    /// Layout of the CSV files
+    #[arg(long, value_enum, default_value = "auto")]
+    layout: CsvLayout,
+
+    /// How the rain column accumulates
+    #[arg(long, value_enum, default_value = "auto")]
+    rain: RainMode,
+
+    /// chrono format of the time column, in station local time, if not one of the common formats
+    #[arg(long)]
+    time_format: Option<String>,
+
+    /// Rows within this many seconds of an existing observation are skipped
+    #[arg(long, default_value = "60")]
+    dedupe_window: i64,
+
+    /// Number of observations written per request
+    #[arg(long, default_value = "1000")]
+    batch_size: usize,
+
+    /// Report what would be imported without writing anything
+    #[arg(long)]
+    dry_run: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Field {
+    Tempc,
+    Tempinc,
+    Humidity,
+    Humidityin,
+    Windspeed,
+    Windgust,
+    Winddir,
+    Rainrate,
+    Rain,
+    Dailyrain,
+    Eventrain,
+    Uv,
+    Solarradiation,
+    Baromrel,
+    Baromabs,
+}
+
+struct Column {
+    index: usize,
+    field: Field,
+    header: String,
+}
+
+fn classify_ecowitt(header: &str) -> Option<Field> {
+    let field = match header {
+        h if h.contains("indoor") && h.contains("temperature") => Field::Tempinc,
+        h if h.contains("indoor") && h.contains("humidity") => Field::Humidityin,
+        h if h.contains("feels like") || h.contains("dew point") => return None,
+        h if h.contains("temperature") => Field::Tempc,
+        h if h.contains("humidity") => Field::Humidity,
+        h if h.contains("wind speed") => Field::Windspeed,
+        h if h.contains("wind gust") => Field::Windgust,
+        h if h.contains("wind direction") => Field::Winddir,
+        h if h.contains("rain rate") => Field::Rainrate,
+        h if h.contains("yearly") => Field::Rain,
+        h if h.contains("daily") => Field::Dailyrain,
+        h if h.contains("event") => Field::Eventrain,
+        h if h.contains("uvi") && !h.contains("rad") => Field::Uv,
+        h if h.contains("solar") => Field::Solarradiation,
+        h if h.contains("relative") => Field::Baromrel,
+        h if h.contains("absolute") => Field::Baromabs,
+        _ => return None,
+    };
+    Some(field)
+}
+
+fn classify_weatherlink(header: &str) -> Option<Field> {
+    // WeatherLink also exports high/low columns for most fields, only the interval values are used
+    let field = match header {
+        h if h.starts_with("high wind speed") => Field::Windgust,
+        h if h.starts_with("high ") || h.starts_with("low ") => return None,
+        h if h.starts_with("inside temp") => Field::Tempinc,
+        h if h.starts_with("inside hum") => Field::Humidityin,
+        h if h.starts_with("temp -") => Field::Tempc,
+        h if h.starts_with("hum -") => Field::Humidity,
+        h if h.starts_with("wind speed") => Field::Windspeed,
+        h if h.starts_with("prevailing wind direction") => Field::Winddir,
+        h if h.starts_with("wind direction") => Field::Winddir,
+        h if h.starts_with("rain rate") => Field::Rainrate,
+        h if h.starts_with("rain -") => Field::Rain,
+        h if h.starts_with("solar rad") => Field::Solarradiation,
+        h if h.starts_with("uv index") => Field::Uv,
+        h if h.starts_with("barometer") => Field::Baromrel,
+        h if h.starts_with("absolute pressure") => Field::Baromabs,
+        _ => return None,
+    };
+    Some(field)
+}
+
+fn detect_layout(headers: &[String]) -> Option<CsvLayout> {
+    if headers
+        .iter()
+        .any(|h| h.contains("outdoor temperature") || h.contains("solar and uvi"))
+    {
+        return Some(CsvLayout::Ecowitt);
+    }
+    if headers.iter().any(|h| h.starts_with("temp -")) {
+        return Some(CsvLayout::Weatherlink);
+    }
+    None
+}
+
+fn convert(field: Field, header: &str, value: f64) -> f64 {
+    let fahrenheit = header.contains('℉') || header.contains("°f");
+    let mph = header.contains("mph");
+    let metres_per_second = header.contains("m/s");
+    let knots = header.contains("knot");
+    let inches = header.contains("in)") || header.contains("- in") || header.contains("in/");
+    let inhg = header.contains("inhg") || header.contains("in hg");
+    let mmhg = header.contains("mmhg");
+
+    match field {
+        Field::Tempc | Field::Tempinc if fahrenheit => fahrenheit_to_celsius(value),
+        Field::Windspeed | Field::Windgust if mph => mph_to_kph(value),
+        Field::Windspeed | Field::Windgust if metres_per_second => value * 3.6,
+        Field::Windspeed | Field::Windgust if knots => value * 1.852,
+        Field::Rainrate | Field::Rain | Field::Dailyrain | Field::Eventrain if inches => {
+            inches_to_mm(value)
+        }
+        Field::Baromrel | Field::Baromabs if inhg => inhg_to_hpa(value),
+        Field::Baromrel | Field::Baromabs if mmhg => value * 1.333224,
+        _ => value,
+    }
+}
+
+fn parse_direction(value: &str) -> Option<f64> {
+    if let Ok(degrees) = value.parse::<f64>() {
+        return Some(degrees);
+    }
+    const CARDINALS: [&str; 16] = [
+        "N", "NNE", "NE", "ENE", "E", "ESE", "SE", "SSE", "S", "SSW", "SW", "WSW", "W", "WNW",
+        "NW", "NNW",
+    ];
+    CARDINALS
+        .iter()
+        .position(|c| c.eq_ignore_ascii_case(value))
+        .map(|i| i as f64 * 22.5)
+}
+
+fn parse_time(value: &str, time_format: &Option<String>) -> Option<DateTime<FixedOffset>> {
+    let naive = match time_format {
+        Some(format) => NaiveDateTime::parse_from_str(value, format).ok(),
+        None => TIME_FORMATS
+            .iter()
+            .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok()),
+    }?;
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .map(|time| time.fixed_offset())
+}
+
+#[derive(Default)]
+struct ImportReport {
+    rows: usize,
+    invalid: usize,
+    duplicates: usize,
+    imported: usize,
+    first: Option<DateTime<FixedOffset>>,
+    last: Option<DateTime<FixedOffset>>,
+}
+
+fn read_observations(
+    args: &ImportArgs,
+    file: &PathBuf,
+    report: &mut ImportReport,
+) -> Result<Vec<Observation>, ApiError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_path(file)
+        .map_err(|err| ApiError::Other(format!("{}: {}", file.display(), err)))?;
+    let headers: Vec<String> = reader
+        .headers()
+        .map_err(|err| ApiError::Other(err.to_string()))?
+        .iter()
+        .map(|h| h.trim_start_matches('\u{feff}').to_lowercase())
+        .collect();
+
+    let layout = match args.layout {
+        CsvLayout::Auto => detect_layout(&headers).ok_or_else(|| {
+            ApiError::Other(format!("{}: unrecognised CSV layout", file.display()))
+        })?,
+        layout => layout,
+    };
+    let rain_mode = match (args.rain, layout) {
+        (RainMode::Auto, CsvLayout::Weatherlink) => RainMode::Interval,
+        (RainMode::Auto, _) => RainMode::Cumulative,
+        (rain_mode, _) => rain_mode,
+    };
+
+    let time_index = headers
+        .iter()
+        .position(|h| h.contains("time") || h.contains("date"))
+        .unwrap_or(0);
+    let mut columns: Vec<Column> = Vec::new();
+    for (index, header) in headers.iter().enumerate() {
+        let field = match layout {
+            CsvLayout::Weatherlink => classify_weatherlink(header),
+            _ => classify_ecowitt(header),
+        };
+        if let Some(field) = field
+            && index != time_index
+            && !columns.iter().any(|c| c.field == field)
+        {
+            columns.push(Column {
+                index,
+                field,
+                header: header.clone(),
+            });
+        }
+    }
+    println!(
+        "{}: {:?} layout, {:?} rain, columns: {}",
+        file.display(),
+        layout,
+        rain_mode,
+        columns
+            .iter()
+            .map(|c| format!("{:?}={}", c.field, c.header))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+
+    let mut observations = Vec::new();
+    let mut rain_total = 0_f64;
+    for record in reader.records() {
+        report.rows += 1;
+        let Ok(record) = record else {
+            report.invalid += 1;
+            continue;
+        };
+        let Some(time) = record
+            .get(time_index)
+            .and_then(|value| parse_time(value, &args.time_format))
+        else {
+            report.invalid += 1;
+            continue;
+        };
+
+        let mut observation = Observation {
+            time,
+            model: format!("import-{:?}", layout).to_lowercase(),
+            ..Default::default()
+        };
+        for column in &columns {
+            let Some(raw) = record
+                .get(column.index)
+                .filter(|v| !v.is_empty() && *v != "--")
+            else {
+                continue;
+            };
+            let value = match column.field {
+                Field::Winddir => parse_direction(raw),
+                _ => raw.parse::<f64>().ok(),
+            }
+            .map(|value| convert(column.field, &column.header, value));
+            match column.field {
+                Field::Tempc => observation.tempc = value,
+                Field::Tempinc => observation.tempinc = value,
+                Field::Humidity => observation.humidity = value,
+                Field::Humidityin => observation.humidityin = value,
+                Field::Windspeed => observation.windspeedkph = value,
+                Field::Windgust => observation.windgustkph = value,
+                Field::Winddir => observation.winddir = value,
+                Field::Rainrate => observation.rainratemm = value,
+                Field::Rain if rain_mode == RainMode::Interval => {
+                    // Interval amounts are summed into a running total, as the queries expect
+                    // totalrainmm to be a counter
+                    rain_total += value.unwrap_or(0_f64).max(0_f64);
+                    observation.totalrainmm = Some(rain_total);
+                }
+                Field::Rain => observation.totalrainmm = value,
+                Field::Dailyrain => observation.dailyrainmm = value,
+                Field::Eventrain => observation.eventrainmm = value,
+                Field::Uv => observation.uv = value,
+                Field::Solarradiation => observation.solarradiation = value,
+                Field::Baromrel => observation.baromrelhpa = value,
+                Field::Baromabs => observation.baromabshpa = value,
+            }
+        }
+        if observation.totalrainmm.is_none() && rain_mode == RainMode::Cumulative {
+            observation.totalrainmm = observation.dailyrainmm;
+        }
+
+        let observation = sanitise(observation);
+        if observation.fields().is_empty() {
+            report.invalid += 1;
+            continue;
+        }
+        observations.push(observation);
+    }
+
+    observations.sort_by_key(|o| o.time);
+    Ok(observations)
+}
+
+fn is_duplicate(
+    existing: &[DateTime<FixedOffset>],
+    time: DateTime<FixedOffset>,
+    window: i64,
+) -> bool {
+    let index = existing.partition_point(|t| *t < time);
+    [index.checked_sub(1), Some(index)]
+        .into_iter()
+        .flatten()
+        .filter_map(|i| existing.get(i))
+        .any(|t| (*t - time).num_seconds().abs() <= window)
+}
+
+pub(crate) async fn run_import(
+    store: Arc<dyn WeatherStore>,
+    args: ImportArgs,
+    cache_path: Option<&Path>,
+    rollups_path: Option<&Path>,
+) -> Result<(), ApiError> {
+    let mut report = ImportReport::default();
+
+    for file in &args.files {
+        let observations = read_observations(&args, file, &mut report)?;
+
+        for batch in observations.chunks(args.batch_size.max(1)) {
+            let (first, last) = (batch.first().unwrap().time, batch.last().unwrap().time);
+            let window = chrono::Duration::seconds(args.dedupe_window);
+            let mut existing = store
+                .observation_times(first - window, last + window + chrono::Duration::seconds(1))
+                .await?;
+            existing.sort();
+
+            let new: Vec<Observation> = batch
+                .iter()
+                .filter(|o| !is_duplicate(&existing, o.time, args.dedupe_window))
+                .cloned()
+                .collect();
+            report.duplicates += batch.len() - new.len();
+            if new.is_empty() {
+                continue;
+            }
+
+            let (first, last) = (new[0].time, new[new.len() - 1].time);
+            report.first = Some(report.first.map_or(first, |f| f.min(first)));
+            report.last = Some(report.last.map_or(last, |l| l.max(last)));
+            report.imported += new.len();
+            if !args.dry_run {
+                store.write(new).await?;
+                if let Some(cache_path) = cache_path {
+                    invalidate_saved(cache_path, first, last)
+                        .await
+                        .map_err(|err| {
+                            ApiError::Other(format!("{}: {}", cache_path.display(), err))
+                        })?;
+                }
+                if let Some(rollups_path) = rollups_path {
+                    // Rain for a day ending at 9am continues into the next
+                    let first = first.with_timezone(&Local).date_naive();
+                    let last = last.with_timezone(&Local).date_naive() + Days::new(1);
+                    mark_stale(rollups_path, first, last).await.map_err(|err| {
+                        ApiError::Other(format!("{}: {}", rollups_path.display(), err))
+                    })?;
+                }
+            }
+        }
+    }
+
+    println!(
+        "{} {} observations from {} rows ({} invalid, {} already stored)",
+        if args.dry_run {
+            "Would import"
+        } else {
+            "Imported"
+        },
+        report.imported,
+        report.rows,
+        report.invalid,
+        report.duplicates
+    );
+    if let (Some(first), Some(last)) = (report.first, report.last) {
+        println!("Covering {} to {}", first.to_rfc3339(), last.to_rfc3339());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(layout: CsvLayout) -> ImportArgs {
+        ImportArgs {
+            files: Vec::new(),
+            layout,
+            rain: RainMode::Auto,
+            time_format: None,
+            dedupe_window: 60,
+            batch_size: 1000,
+            dry_run: true,
+        }
+    }
+
+    fn import(name: &str, csv: &str) -> (Vec<Observation>, ImportReport) {
+        let file =
+            std::env::temp_dir().join(format!("kom-import-{}-{name}.csv", std::process::id()));
+        std::fs::write(&file, csv).unwrap();
+        let mut report = ImportReport::default();
+        let observations = read_observations(&args(CsvLayout::Auto), &file, &mut report);
+        std::fs::remove_file(&file).unwrap();
+        (observations.unwrap(), report)
+    }
+
+    fn close(value: Option<f64>, expected: f64) -> bool {
+        value.is_some_and(|value| (value - expected).abs() < 0.01)
+    }
+
+    #[test]
+    fn ecowitt_export() {
+        let (observations, report) = import(
+            "ecowitt",
+            "\u{feff}Time,Indoor Temperature(℃),Outdoor Temperature(℃),Feels Like(℃),Outdoor Humidity(%),\
+             Wind Speed(km/h),Wind Direction(°),Rain Rate(mm/hr),Daily(mm),Yearly(mm),Relative(hPa)\n\
+             2025-01-01 00:05,21.5,18.2,17.9,64,12.1,270,0,1.2,301.4,1015.2\n\
+             2025-01-01 00:00,21.4,18.4,18.0,63,--,,0,1.0,301.2,1015.1\n\
+             not a time,21.4,18.4,18.0,63,9.0,250,0,1.0,301.2,1015.1\n",
+        );
+
+        assert_eq!(report.rows, 3);
+        assert_eq!(report.invalid, 1);
+        assert_eq!(observations.len(), 2);
+        // Rows are sorted by time
+        assert!(observations[0].time < observations[1].time);
+        let observation = &observations[1];
+        assert_eq!(observation.model, "import-ecowitt");
+        assert!(close(observation.tempinc, 21.5));
+        assert!(close(observation.tempc, 18.2));
+        assert!(close(observation.humidity, 64.0));
+        assert!(close(observation.windspeedkph, 12.1));
+        assert!(close(observation.winddir, 270.0));
+        assert!(close(observation.dailyrainmm, 1.2));
+        assert!(close(observation.totalrainmm, 301.4));
+        assert!(close(observation.baromrelhpa, 1015.2));
+        // Missing values are left out rather than read as zero
+        assert_eq!(observations[0].windspeedkph, None);
+        assert_eq!(observations[0].winddir, None);
+    }
+
+    #[test]
+    fn weatherlink_export() {
+        let (observations, report) = import(
+            "weatherlink",
+            "Date & Time,Inside Temp - °F,Temp - °F,High Temp - °F,Low Temp - °F,Hum - %,\
+             Wind Speed - mph,High Wind Speed - mph,Prevailing Wind Direction,Rain - in,Barometer - in Hg\n\
+             1/1/25 12:00 AM,68.0,50.0,51.0,49.0,80,5.0,10.0,NNW,0.01,29.92\n\
+             1/1/25 12:15 AM,68.0,50.0,51.0,49.0,80,5.0,10.0,N,0.02,29.92\n",
+        );
+
+        assert_eq!(report.invalid, 0);
+        assert_eq!(observations.len(), 2);
+        let observation = &observations[0];
+        assert_eq!(observation.model, "import-weatherlink");
+        assert!(close(observation.tempinc, 20.0));
+        assert!(close(observation.tempc, 10.0));
+        assert!(close(observation.windspeedkph, 8.05));
+        assert!(close(observation.windgustkph, 16.09));
+        assert!(close(observation.winddir, 337.5));
+        assert!(close(observation.baromrelhpa, 1013.21));
+        // Interval rain is summed into a running total
+        assert!(close(observations[0].totalrainmm, 0.254));
+        assert!(close(observations[1].totalrainmm, 0.762));
+    }
+
+    #[test]
+    fn unrecognised_layout() {
+        let file =
+            std::env::temp_dir().join(format!("kom-import-{}-unknown.csv", std::process::id()));
+        std::fs::write(&file, "When,Value\n2025-01-01 00:00,1\n").unwrap();
+        let result = read_observations(&args(CsvLayout::Auto), &file, &mut ImportReport::default());
+        std::fs::remove_file(&file).unwrap();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn imperial_and_metric_units() {
+        assert!((convert(Field::Tempc, "outdoor temperature(℉)", 212.0) - 100.0).abs() < 1e-9);
+        assert_eq!(convert(Field::Tempc, "outdoor temperature(℃)", 21.0), 21.0);
+        assert!((convert(Field::Windspeed, "wind speed(mph)", 10.0) - 16.09344).abs() < 1e-6);
+        assert!((convert(Field::Windspeed, "wind speed(m/s)", 10.0) - 36.0).abs() < 1e-9);
+        assert!((convert(Field::Windgust, "wind gust(knots)", 10.0) - 18.52).abs() < 1e-9);
+        assert_eq!(convert(Field::Windspeed, "wind speed(km/h)", 10.0), 10.0);
+        assert!((convert(Field::Dailyrain, "daily(in)", 1.0) - 25.4).abs() < 1e-9);
+        assert!((convert(Field::Rainrate, "rain rate(in/hr)", 1.0) - 25.4).abs() < 1e-9);
+        assert_eq!(convert(Field::Dailyrain, "daily(mm)", 1.0), 1.0);
+        assert!((convert(Field::Baromrel, "relative(inhg)", 29.92) - 1013.21).abs() < 0.01);
+        assert!((convert(Field::Baromabs, "absolute(mmhg)", 760.0) - 1013.25).abs() < 0.01);
+        assert_eq!(convert(Field::Baromrel, "relative(hpa)", 1013.0), 1013.0);
+        // Units only apply to the fields they measure
+        assert_eq!(convert(Field::Humidity, "humidity(%) in", 50.0), 50.0);
+    }
+
+    #[test]
+    fn duplicate_rows() {
+        let time = |value: &str| DateTime::parse_from_rfc3339(value).unwrap();
+        let existing = [
+            time("2025-01-01T00:00:00Z"),
+            time("2025-01-01T00:05:00Z"),
+            time("2025-01-01T00:10:00Z"),
+        ];
+
+        assert!(is_duplicate(&existing, time("2025-01-01T00:05:00Z"), 60));
+        assert!(is_duplicate(&existing, time("2025-01-01T00:04:00Z"), 60));
+        assert!(is_duplicate(&existing, time("2025-01-01T00:11:00Z"), 60));
+        assert!(!is_duplicate(&existing, time("2025-01-01T00:07:30Z"), 60));
+        assert!(!is_duplicate(&existing, time("2025-01-01T00:11:01Z"), 60));
+        assert!(!is_duplicate(&[], time("2025-01-01T00:05:00Z"), 60));
+    }
+}
@@ -0,0 +1,107 @@
+use crate::types::Observation;
+use crate::{ApiError, ServerState};
+use chrono::{DateTime, FixedOffset, NaiveDateTime, Utc};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+pub(crate) fn parse_param(params: &HashMap<String, String>, key: &str) -> Option<f64> {
+    params
+        .get(key)
+        .and_then(|value| value.trim().parse::<f64>().ok())
+        .filter(|value| value.is_finite())
+}
+
+pub(crate) fn parse_date_utc(params: &HashMap<String, String>) -> DateTime<FixedOffset> {
+    // Both Ecowitt and WU send "YYYY-MM-DD HH:MM:SS" in UTC, or "now" if the station has no clock
+    params
+        .get("dateutc")
+        .and_then(|value| NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S").ok())
+        .map(|value| value.and_utc())
+        .unwrap_or_else(Utc::now)
+        .fixed_offset()
+}
+
+fn plausible(value: Option<f64>, min: f64, max: f64) -> Option<f64> {
+    value.filter(|v| *v >= min && *v <= max)
+}
+
+// Stations send placeholder values such as -9999 for sensors that are not connected,
+// so anything outside of these (generous) bounds is discarded rather than stored
+pub(crate) fn sanitise(observation: Observation) -> Observation {
+    Observation {
+        tempc: plausible(observation.tempc, -70.0, 70.0),
+        tempinc: plausible(observation.tempinc, -40.0, 70.0),
+        humidity: plausible(observation.humidity, 0.0, 100.0),
+        humidityin: plausible(observation.humidityin, 0.0, 100.0),
+        windspeedkph: plausible(observation.windspeedkph, 0.0, 400.0),
+        windgustkph: plausible(observation.windgustkph, 0.0, 400.0),
+        winddir: plausible(observation.winddir, 0.0, 360.0),
+        rainratemm: plausible(observation.rainratemm, 0.0, 2000.0),
+        totalrainmm: plausible(observation.totalrainmm, 0.0, 1_000_000.0),
+        dailyrainmm: plausible(observation.dailyrainmm, 0.0, 2000.0),
+        eventrainmm: plausible(observation.eventrainmm, 0.0, 10_000.0),
+        uv: plausible(observation.uv, 0.0, 25.0),
+        solarradiation: plausible(observation.solarradiation, 0.0, 2000.0),
+        baromrelhpa: plausible(observation.baromrelhpa, 850.0, 1100.0),
+        baromabshpa: plausible(observation.baromabshpa, 300.0, 1100.0),
+        ..observation
+    }
+}
+
+pub(crate) async fn write_observation(
+    state: &Arc<ServerState>,
+    observation: Observation,
+) -> Result<(), ApiError> {
+    if observation.fields().is_empty() {
+        return Err(ApiError::BadRequest(
+            "Observation contained no usable values".to_string(),
+        ));
+    }
+
+    let time = observation.time;
+    state.store.write(vec![observation]).await?;
+    state.cache.invalidate(time, time).await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn discards_implausible_values() {
+        let observation = sanitise(Observation {
+            tempc: Some(-9999.0),
+            humidity: Some(101.0),
+            winddir: Some(360.0),
+            windspeedkph: Some(-1.0),
+            baromrelhpa: Some(1013.0),
+            baromabshpa: Some(0.0),
+            uv: Some(0.0),
+            ..Default::default()
+        });
+        assert_eq!(observation.tempc, None);
+        assert_eq!(observation.humidity, None);
+        assert_eq!(observation.winddir, Some(360.0));
+        assert_eq!(observation.windspeedkph, None);
+        assert_eq!(observation.baromrelhpa, Some(1013.0));
+        assert_eq!(observation.baromabshpa, None);
+        assert_eq!(observation.uv, Some(0.0));
+    }
+
+    #[test]
+    fn parses_dates() {
+        let params = HashMap::from([("dateutc".to_string(), "2025-01-10 03:04:05".to_string())]);
+        assert_eq!(
+            parse_date_utc(&params).to_rfc3339(),
+            "2025-01-10T03:04:05+00:00"
+        );
+        let params = HashMap::from([("dateutc".to_string(), "now".to_string())]);
+        assert!(
+            (Utc::now().fixed_offset() - parse_date_utc(&params))
+                .num_seconds()
+                .abs()
+                < 5
+        );
+    }
+}
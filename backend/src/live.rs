@@ -0,0 +1,147 @@
+use crate::rain::RainAccumulator;
+use crate::resolution::Resolution;
+use crate::today::TodayTracker;
+use crate::types::HourRecordWithDerivedTypes;
+use crate::{ApiError, ServerState, derive_hour_records};
+use axum::extract::State;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::response::Response;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use chrono::{DateTime, Duration, Local};
+use futures::{Stream, StreamExt};
+use std::convert::Infallible;
+use std::sync::Arc;
+use tokio::sync::watch;
+use tokio_stream::wrappers::WatchStream;
+
+// The daily rollups are brought up to date at most this often, and when the day changes, rather
+// than with every observation
+const ROLLUP_REFRESH_MINUTES: i64 = 15;
+
+/// The latest observation with its derived fields, or None until the first has been read
+pub(crate) type LiveSender = watch::Sender<Option<HourRecordWithDerivedTypes>>;
+
+/// Reads any observations newer than those already tracked, and publishes the latest to live
+/// clients and today's conditions
+async fn poll(
+    state: &ServerState,
+    today: &mut TodayTracker,
+    rollups_refreshed: &mut Option<DateTime<Local>>,
+) -> Result<(), ApiError> {
+    let Some(latest) = state.store.latest().await? else {
+        return Ok(());
+    };
+    let previous = today.latest_time();
+    if previous.is_some_and(|previous| previous >= latest.time) {
+        return Ok(());
+    }
+
+    // The previous observation is read again, to measure the rain since it
+    let mut data = state
+        .store
+        .range(
+            previous.unwrap_or_else(|| today.backfill_start(latest.time)),
+            latest.time + Duration::seconds(1),
+            Resolution::Raw,
+        )
+        .await?;
+    data.sort_by_key(|r| r.time);
+
+    let last_two = data[data.len().saturating_sub(2)..].to_vec();
+    let live = derive_hour_records(
+        last_two,
+        &mut RainAccumulator::default(),
+        state,
+        Resolution::Raw,
+    );
+    if let Some(observation) = live.into_iter().last() {
+        state.live.send_replace(Some(observation));
+    }
+
+    for datum in data {
+        if previous.is_none_or(|previous| datum.time > previous) {
+            today.add(state, datum);
+        }
+    }
+    let now = Local::now();
+    let rollups = match *rollups_refreshed {
+        Some(refreshed)
+            if refreshed.date_naive() == now.date_naive()
+                && now - refreshed < Duration::minutes(ROLLUP_REFRESH_MINUTES) =>
+        {
+            Ok(())
+        }
+        _ => state
+            .rollups
+            .refresh(state)
+            .await
+            .inspect(|_| *rollups_refreshed = Some(now)),
+    };
+    let records = today.refresh_records(state).await;
+    state.today.send_replace(today.snapshot(state));
+    rollups.and(records)
+}
+
+/// Polls the store for new observations, so that observations written by other listeners are
+/// picked up as well as those received directly
+pub(crate) async fn poll_observations(state: Arc<ServerState>, interval: std::time::Duration) {
+    let mut today = TodayTracker::new(state.rain_day);
+    let mut rollups_refreshed = None;
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        ticker.tick().await;
+        if let Err(err) = poll(&state, &mut today, &mut rollups_refreshed).await {
+            eprintln!("Polling for new observations failed: {}", err);
+        }
+    }
+}
+
+pub(crate) async fn live_events(
+    State(state): State<Arc<ServerState>>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let stream = WatchStream::new(state.live.subscribe())
+        .filter_map(|observation| async move { observation })
+        .map(|observation| {
+            Ok(Event::default()
+                .event("observation")
+                .json_data(observation)
+                .unwrap())
+        });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+pub(crate) async fn live_socket(
+    State(state): State<Arc<ServerState>>,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    upgrade.on_upgrade(move |socket| send_observations(socket, state))
+}
+
+async fn send_observations(mut socket: WebSocket, state: Arc<ServerState>) {
+    let mut receiver = state.live.subscribe();
+    receiver.mark_changed();
+    loop {
+        tokio::select! {
+            changed = receiver.changed() => {
+                if changed.is_err() {
+                    return;
+                }
+                let observation = receiver.borrow_and_update().clone();
+                let Some(observation) = observation else {
+                    continue;
+                };
+                let json = serde_json::to_string(&observation).unwrap();
+                if socket.send(Message::Text(json.into())).await.is_err() {
+                    return;
+                }
+            }
+            // Anything sent by the client is ignored, but reading is needed to notice it leave
+            message = socket.recv() => {
+                if !matches!(message, Some(Ok(_))) {
+                    return;
+                }
+            }
+        }
+    }
+}
@@ -1,32 +1,98 @@
+mod cache;
+mod climate;
+mod daily;
+mod ecowitt;
+mod export;
 mod flux;
+mod forecast;
+mod import;
+mod ingest;
+mod live;
+mod observations;
+mod pressure;
+mod rain;
+mod records;
+mod resolution;
+mod rollup;
+mod sky;
+mod stats;
+mod store;
+mod thermodynamics;
+mod today;
 mod types;
+mod units;
+mod wind;
+mod windrose;
+mod wunderground;
+mod yearly;
 
 use axum::{
     Json, Router,
     extract::{Query, State},
     http::StatusCode,
     response::IntoResponse,
-    routing::get,
+    routing::{get, post},
 };
-use chrono::{DateTime, Local, NaiveTime};
-use clap::Parser;
+use chrono::{DateTime, Duration, FixedOffset};
+use clap::{Parser, Subcommand, ValueEnum};
 use dotenvy::dotenv_override;
 use influxdb2::Client;
 use serde::Deserialize;
+use std::path::PathBuf;
 use std::sync::Arc;
-use sunrise::{Coordinates, SolarDay, SolarEvent};
+use sunrise::Coordinates;
 use thiserror::Error;
 use tokio::signal;
 use tower_http::services::{ServeDir, ServeFile};
 use tracing_subscriber::EnvFilter;
 
-use crate::ApiError::Other;
-use crate::flux::{build_monthly_flux, build_range_flux, query_flux, query_flux_month_records};
-use crate::types::{HourRecordWithDerivedTypes, TodayDataWithDerivedTypes};
+use crate::ApiError::BadRequest;
+use crate::cache::RangeCache;
+use crate::climate::{anomalies, climate, month_normals, station_months};
+use crate::daily::daily;
+use crate::ecowitt::ecowitt;
+use crate::export::export;
+use crate::forecast::forecast;
+use crate::import::{ImportArgs, run_import};
+use crate::live::{LiveSender, live_events, live_socket, poll_observations};
+use crate::observations::observations;
+use crate::pressure::observation_sea_level_pressure;
+use crate::rain::{RainAccumulator, RainDayBoundary};
+use crate::records::all_records;
+use crate::resolution::Resolution;
+use crate::rollup::{DailyRollups, rebuild_rollups};
+use crate::sky::{classify, mean_clear_sky_radiation};
+use crate::stats::month_records;
+use crate::store::{InfluxQlStore, InfluxStore, SqliteStore, WeatherStore};
+use crate::thermodynamics::Thermodynamics;
+use crate::today::{TodaySender, today};
+use crate::types::{HourRecordFlux, HourRecordWithDerivedTypes, MonthRecordWithDerivedTypes};
+use crate::windrose::windrose;
+use crate::wunderground::wunderground;
+use crate::yearly::yearly;
+
+#[derive(ValueEnum, Clone, Debug)]
+enum StoreKind {
+    /// InfluxDB 2.x, queried using Flux
+    Influx,
+    /// InfluxDB 1.x, queried using InfluxQL
+    Influxql,
+    /// Embedded SQLite database
+    Sqlite,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Import historical observations from Ecowitt or WeatherLink CSV exports
+    Import(ImportArgs),
+}
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Config {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Latitude
     #[arg(long, env = "LAT")]
     lat: f64,
@@ -35,22 +101,93 @@ struct Config {
     #[arg(long, env = "LONG")]
     long: f64,
 
+    /// Station elevation in metres, for reducing absolute pressure to mean sea level. Without it
+    /// the station's relative pressure is used.
+    #[arg(long, env = "ELEVATION")]
+    elevation: Option<f64>,
+
+    /// When each day's rainfall is reset, in the station's timezone
+    #[arg(long, env = "RAIN_DAY", value_enum, default_value = "midnight")]
+    rain_day: RainDayBoundary,
+
+    /// How often to check the store for new observations to send to live clients, in seconds
+    #[arg(long, env = "LIVE_POLL_SECONDS", default_value = "10", value_parser = clap::value_parser!(u64).range(1..))]
+    live_poll_seconds: u64,
+
+    /// How long results for periods that may still change are cached, in seconds
+    #[arg(long, env = "CACHE_TTL_SECONDS", default_value = "60")]
+    cache_ttl_seconds: i64,
+
+    /// File to keep the daily summaries behind the monthly, yearly and record statistics in across
+    /// restarts
+    #[arg(long, env = "ROLLUPS_PATH")]
+    rollups_path: Option<PathBuf>,
+
+    /// Bearer token for administrative requests such as rebuilding the daily summaries, which
+    /// are refused if unset
+    #[arg(long, env = "ADMIN_TOKEN")]
+    admin_token: Option<String>,
+
+    /// Directory to keep cached results for past periods in across restarts
+    #[arg(long, env = "CACHE_PATH")]
+    cache_path: Option<PathBuf>,
+
+    /// Storage backend for weather data
+    #[arg(long, env = "STORE", value_enum, default_value = "influx")]
+    store: StoreKind,
+
+    /// SQLite database path, used by the sqlite store
+    #[arg(long, env = "SQLITE_PATH", default_value = "kom.sqlite")]
+    sqlite_path: String,
+
     /// InfluxDB URL
     #[arg(long, env = "INFLUX_URL")]
-    influx_url: String,
+    influx_url: Option<String>,
 
     /// InfluxDB Token
     #[arg(long, env = "INFLUX_TOKEN")]
-    influx_token: String,
+    influx_token: Option<String>,
 
     /// InfluxDB Bucket
     #[arg(long, env = "INFLUX_BUCKET")]
-    influx_bucket: String,
+    influx_bucket: Option<String>,
 
     /// InfluxDB Organization
     #[arg(long, env = "INFLUX_ORG", default_value = "default")]
     influx_org: String,
 
+    /// InfluxDB 1.x database, used by the influxql store
+    #[arg(long, env = "INFLUX_DATABASE")]
+    influx_database: Option<String>,
+
+    /// InfluxDB 1.x retention policy, the database default is used if unset
+    #[arg(long, env = "INFLUX_RETENTION_POLICY")]
+    influx_retention_policy: Option<String>,
+
+    /// InfluxDB 1.x username
+    #[arg(long, env = "INFLUX_USERNAME")]
+    influx_username: Option<String>,
+
+    /// InfluxDB 1.x password
+    #[arg(long, env = "INFLUX_PASSWORD")]
+    influx_password: Option<String>,
+
+    /// InfluxDB Measurement
+    #[arg(long, env = "INFLUX_MEASUREMENT", default_value = "weather")]
+    influx_measurement: String,
+
+    /// Ecowitt PASSKEY that uploads must match, any station is accepted if unset
+    #[arg(long, env = "ECOWITT_PASSKEY")]
+    ecowitt_passkey: Option<String>,
+
+    /// Weather Underground station ID that uploads must match, any station is accepted if unset
+    #[arg(long, env = "WU_STATION_ID")]
+    wu_station_id: Option<String>,
+
+    /// Weather Underground station password that uploads must match
+    #[arg(long, env = "WU_PASSWORD")]
+    wu_password: Option<String>,
+
     /// Hostname to bind to
     #[arg(long, env = "HOSTNAME", default_value = "0.0.0.0")]
     host_name: String,
@@ -62,9 +199,18 @@ struct Config {
 
 #[derive(Clone)]
 struct ServerState {
-    client: Client,
-    bucket: String,
+    store: Arc<dyn WeatherStore>,
     coordinates: Coordinates,
+    elevation: Option<f64>,
+    rain_day: RainDayBoundary,
+    ecowitt_passkey: Option<String>,
+    wu_station_id: Option<String>,
+    wu_password: Option<String>,
+    admin_token: Option<String>,
+    live: LiveSender,
+    today: TodaySender,
+    rollups: Arc<DailyRollups>,
+    cache: Arc<RangeCache>,
 }
 
 #[derive(Debug, Error)]
@@ -72,6 +218,21 @@ enum ApiError {
     #[error("Influx query failed: {0}")]
     Influx(#[from] influxdb2::RequestError),
 
+    #[error("InfluxQL query failed: {0}")]
+    InfluxQl(String),
+
+    #[error("SQLite query failed: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+
+    #[error("Bad request: {0}")]
+    BadRequest(String),
+
+    #[error("Unauthorised")]
+    Unauthorised,
+
+    #[error("Unavailable: {0}")]
+    Unavailable(String),
+
     #[error("Unexpected error: {0}")]
     Other(String),
 }
@@ -79,15 +240,30 @@ enum ApiError {
 impl IntoResponse for ApiError {
     fn into_response(self) -> axum::response::Response {
         println!("API error: {}", self);
+        let status = match self {
+            ApiError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            ApiError::Unauthorised => StatusCode::UNAUTHORIZED,
+            ApiError::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
         (
-            StatusCode::INTERNAL_SERVER_ERROR,
+            status,
             Json(serde_json::json!({ "error": self.to_string() })),
         )
             .into_response()
     }
 }
 
-fn feels_like_temperature(tempc: f64, humidity: f64, windspeedkph: f64) -> f64 {
+/// Apparent temperature, if both temperature and humidity were reported. Missing wind is taken
+/// as calm.
+fn feels_like_temperature(
+    tempc: Option<f64>,
+    humidity: Option<f64>,
+    windspeedkph: Option<f64>,
+) -> Option<f64> {
+    let (tempc, humidity) = tempc.zip(humidity)?;
+    let windspeedkph = windspeedkph.unwrap_or(0_f64);
+
     // Australian Apparent Temperature (BOM)
     // Ref: http://www.bom.gov.au/info/thermal_comfort/
     // AT = Ta + 0.33×e − 0.70×ws − 4.00
@@ -102,7 +278,7 @@ fn feels_like_temperature(tempc: f64, humidity: f64, windspeedkph: f64) -> f64 {
     // e = (rh / 100) * 6.105 * exp((17.27 * Ta) / (237.7 + Ta))
     let e = (humidity / 100.0) * 6.105 * ((17.27 * ta) / (237.7 + ta)).exp();
 
-    ta + (0.33 * e) - (0.70 * ws_ms) - 4.00
+    Some(ta + (0.33 * e) - (0.70 * ws_ms) - 4.00)
 }
 
 #[derive(Deserialize)]
@@ -111,43 +287,33 @@ struct RangeParams {
     end: String,
 }
 
-fn validate_range_params(params: &RangeParams) -> bool {
+fn parse_range_params(
+    params: &RangeParams,
+) -> Result<(DateTime<FixedOffset>, DateTime<FixedOffset>), ApiError> {
     let start = DateTime::parse_from_rfc3339(&params.start);
-
-    if let Err(_parse_error) = start {
-        return false;
-    }
-
     let end = DateTime::parse_from_rfc3339(&params.end);
-    if let Err(_parse_error) = end {
-        return false;
-    }
 
-    if end.unwrap().lt(&start.unwrap()) {
-        return false;
+    match (start, end) {
+        (Ok(start), Ok(end)) if start <= end => Ok((start, end)),
+        _ => Err(BadRequest("Invalid range".to_string())),
     }
-
-    true
 }
 
-async fn past(
-    State(state): State<Arc<ServerState>>,
-    Query(params): Query<RangeParams>,
-) -> Result<Json<Vec<HourRecordWithDerivedTypes>>, ApiError> {
-    if !validate_range_params(&params) {
-        return Err(Other("Invalid range".to_string()));
-    }
-
-    let flux = build_range_flux(&state.bucket, &params.start, &params.end);
-    let mut data = query_flux(&state, &flux).await?;
-    data.sort_by_key(|r| r.time);
-
-    let mut last_total_rain = data.first().unwrap().totalrainmm.max(0_f64);
+/// Adds derived fields to each record and replaces the cumulative totalrainmm with the rain since
+/// the previous record. Without a previous record the first only provides the starting rain
+/// counters, so is dropped.
+fn derive_hour_records(
+    data: Vec<HourRecordFlux>,
+    rain: &mut RainAccumulator,
+    state: &ServerState,
+    resolution: Resolution,
+) -> Vec<HourRecordWithDerivedTypes> {
+    let window = Duration::seconds(resolution.seconds().unwrap_or(0));
     let mut result: Vec<HourRecordWithDerivedTypes> = Vec::new();
     for datum in data {
-        let next_total_rain = datum.totalrainmm.max(0_f64);
-        let delta = (next_total_rain - last_total_rain).max(0_f64);
-        last_total_rain = next_total_rain;
+        let Some(delta) = rain.add((&datum).into()) else {
+            continue;
+        };
 
         let mut result_datum = HourRecordWithDerivedTypes::from(datum);
         result_datum.totalrainmm = delta;
@@ -157,73 +323,128 @@ async fn past(
             result_datum.windspeedkph,
         );
         result_datum.feelslikein =
-            feels_like_temperature(result_datum.tempinc, result_datum.humidityin, 0_f64);
+            feels_like_temperature(result_datum.tempinc, result_datum.humidityin, None);
+        let thermodynamics = Thermodynamics::from_readings(
+            result_datum.tempc,
+            result_datum.humidity,
+            result_datum.windspeedkph,
+        );
+        result_datum.dewpoint = thermodynamics.map(|t| t.dewpoint);
+        result_datum.heatindex = thermodynamics.map(|t| t.heatindex);
+        result_datum.windchill = thermodynamics.map(|t| t.windchill);
+        result_datum.humidex = thermodynamics.map(|t| t.humidex);
+        result_datum.wetbulb = thermodynamics.map(|t| t.wetbulb);
+        result_datum.absolutehumidity = thermodynamics.map(|t| t.absolutehumidity);
+        result_datum.cloudbase = thermodynamics.map(|t| t.cloudbase);
+        result_datum.baromslhpa = observation_sea_level_pressure(
+            result_datum.baromabshpa,
+            result_datum.baromrelhpa,
+            state.elevation,
+            result_datum.tempc,
+        );
+        let conditions = classify(
+            result_datum.solarradiation,
+            mean_clear_sky_radiation(result_datum.time, window, &state.coordinates),
+            result_datum.totalrainmm > 0_f64
+                || result_datum.rainratemm.is_some_and(|rate| rate > 0_f64),
+        );
+        result_datum.clearskyradiation = conditions.clearskyradiation;
+        result_datum.clearnessindex = conditions.clearnessindex;
+        result_datum.cloudcover = conditions.cloudcover;
+        result_datum.sky = conditions.sky;
         result.push(result_datum);
     }
-    result.remove(0);
+    result
+}
 
-    Ok(Json(result))
+#[derive(Deserialize)]
+struct PastParams {
+    #[serde(flatten)]
+    range: RangeParams,
+    resolution: Option<Resolution>,
 }
 
-async fn today(State(state): State<Arc<ServerState>>) -> Result<Json<serde_json::Value>, ApiError> {
-    let end = Local::now();
-    let start = end
-        .with_time(NaiveTime::from_hms_opt(0, 0, 0).unwrap())
-        .unwrap();
-    let flux = build_range_flux(&state.bucket, &start.to_rfc3339(), &end.to_rfc3339());
-    let mut data = query_flux(&state, &flux).await?;
+async fn past(
+    State(state): State<Arc<ServerState>>,
+    Query(params): Query<PastParams>,
+) -> Result<Json<Vec<HourRecordWithDerivedTypes>>, ApiError> {
+    let (start, end) = parse_range_params(&params.range)?;
+    let resolution = params
+        .resolution
+        .unwrap_or_else(|| Resolution::for_range(end - start));
+    let mut data = state
+        .cache
+        .range(state.store.as_ref(), start, end, resolution)
+        .await?;
     data.sort_by_key(|r| r.time);
 
-    let last = data.last().unwrap();
-    let mut result = TodayDataWithDerivedTypes::default();
-
-    result.time = last.time;
-    result.tempc = last.tempc;
-    result.tempinc = last.tempinc;
-    result.humidity = last.humidity;
-    result.humidityin = last.humidityin;
-    result.windspeedkph = last.windspeedkph;
-    result.windgustkph = last.windgustkph;
-    result.winddir = last.winddir.clone();
-    result.rainratemm = last.rainratemm;
-    result.totalrainmm = last.totalrainmm - data.first().unwrap().totalrainmm;
-    result.uv = last.uv;
-    let solar_day = SolarDay::new(state.coordinates, Local::now().date_naive());
-    result.sunrise = solar_day
-        .event_time(SolarEvent::Sunrise)
-        .with_timezone(&Local)
-        .to_rfc3339();
-    result.sunset = solar_day
-        .event_time(SolarEvent::Sunset)
-        .with_timezone(&Local)
-        .to_rfc3339();
-    result.solarradiation = last.solarradiation;
-    result.feelslike = feels_like_temperature(result.tempc, result.humidity, result.windspeedkph);
-    result.feelslikein = feels_like_temperature(result.tempinc, result.humidityin, 0_f64);
-    for datum in data {
-        result.mintemp = result.mintemp.min(datum.tempc);
-        result.maxtemp = result.maxtemp.max(datum.tempc);
-        result.mintempin = result.mintempin.min(datum.tempinc);
-        result.maxtempin = result.maxtempin.max(datum.tempinc);
-        result.maxuv = result.maxuv.max(datum.uv);
-    }
+    let result = derive_hour_records(data, &mut RainAccumulator::default(), &state, resolution);
 
-    Ok(Json(serde_json::to_value(result).unwrap()))
+    Ok(Json(result))
+}
+
+/// Mean sea-level pressure of an observation, and its change from the observation closest to three
+/// hours earlier (within 15 minutes either side)
+async fn pressure_trend(
+    state: &ServerState,
+    observation: &HourRecordFlux,
+) -> Result<(Option<f64>, Option<f64>), ApiError> {
+    let target = observation.time - Duration::hours(3);
+    let window = Duration::minutes(15);
+    let data = state
+        .store
+        .range(target - window, target + window, Resolution::Raw)
+        .await?;
+    Ok(pressure_change(state, observation, &data))
+}
+
+/// As pressure_trend, picking the earlier observation from those given
+fn pressure_change<'a>(
+    state: &ServerState,
+    observation: &HourRecordFlux,
+    data: impl IntoIterator<Item = &'a HourRecordFlux>,
+) -> (Option<f64>, Option<f64>) {
+    let sea_level_pressure = |datum: &HourRecordFlux| {
+        observation_sea_level_pressure(
+            datum.baromabshpa,
+            datum.baromrelhpa,
+            state.elevation,
+            datum.tempc,
+        )
+    };
+    let Some(current) = sea_level_pressure(observation) else {
+        return (None, None);
+    };
+
+    let target = observation.time - Duration::hours(3);
+    let window = Duration::minutes(15);
+    let earlier = data
+        .into_iter()
+        .filter(|datum| (datum.time - target).abs() <= window)
+        .filter_map(|datum| Some(((datum.time - target).abs(), sea_level_pressure(datum)?)))
+        .min_by_key(|(offset, _)| *offset)
+        .map(|(_, pressure)| pressure);
+
+    (Some(current), earlier.map(|earlier| current - earlier))
 }
 
 async fn monthly(
     State(state): State<Arc<ServerState>>,
     Query(params): Query<RangeParams>,
 ) -> Result<Json<serde_json::Value>, ApiError> {
-    if !validate_range_params(&params) {
-        return Err(Other("Invalid range".to_string()));
-    }
-
-    let flux = build_monthly_flux(&state.bucket, &params.start, &params.end);
-    let mut data = query_flux_month_records(&state, &flux).await?;
-    data.sort_by_key(|r| r.time);
-
-    Ok(Json(serde_json::to_value(data).unwrap()))
+    let (start, end) = parse_range_params(&params)?;
+    // Worked out from the daily rollups in memory, so cheap enough not to need the range cache
+    let data = month_records(&state.rollups.range(&state, start, end).await?);
+
+    let normals = month_normals(&station_months(&state).await?);
+    let result: Vec<MonthRecordWithDerivedTypes> = data
+        .into_iter()
+        .map(|record| MonthRecordWithDerivedTypes {
+            anomalies: anomalies(&record, &normals),
+            ..record.into()
+        })
+        .collect();
+    Ok(Json(serde_json::to_value(result).unwrap()))
 }
 
 async fn shutdown_signal() {
@@ -246,6 +467,64 @@ async fn shutdown_signal() {
     }
 }
 
+fn build_store(config: &Config) -> Arc<dyn WeatherStore> {
+    match config.store {
+        StoreKind::Influx => {
+            let influx_url = config
+                .influx_url
+                .clone()
+                .expect("INFLUX_URL is required for the influx store");
+            let influx_bucket = config
+                .influx_bucket
+                .clone()
+                .expect("INFLUX_BUCKET is required for the influx store");
+            println!(
+                "Connecting to InfluxDB server={} org={} bucket={}",
+                influx_url, config.influx_org, influx_bucket
+            );
+            let client = Client::new(
+                influx_url,
+                config.influx_org.clone(),
+                config
+                    .influx_token
+                    .clone()
+                    .expect("INFLUX_TOKEN is required for the influx store"),
+            );
+            Arc::new(InfluxStore::new(
+                client,
+                influx_bucket,
+                config.influx_measurement.clone(),
+            ))
+        }
+        StoreKind::Influxql => {
+            let influx_url = config
+                .influx_url
+                .clone()
+                .expect("INFLUX_URL is required for the influxql store");
+            let influx_database = config
+                .influx_database
+                .clone()
+                .expect("INFLUX_DATABASE is required for the influxql store");
+            println!(
+                "Connecting to InfluxDB 1.x server={} database={}",
+                influx_url, influx_database
+            );
+            Arc::new(InfluxQlStore::new(
+                influx_url,
+                influx_database,
+                config.influx_retention_policy.clone(),
+                config.influx_measurement.clone(),
+                config.influx_username.clone(),
+                config.influx_password.clone(),
+            ))
+        }
+        StoreKind::Sqlite => {
+            println!("Opening SQLite database path={}", config.sqlite_path);
+            Arc::new(SqliteStore::open(&config.sqlite_path).unwrap())
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() {
     dotenv_override().ok();
@@ -259,23 +538,67 @@ async fn main() {
 
     let binding_address = format!("{}:{}", config.host_name, config.port_number);
 
-    println!(
-        "Connecting to InfluxDB server={} org={} bucket={}",
-        config.influx_url, config.influx_org, config.influx_bucket
-    );
-    let client = Client::new(config.influx_url, config.influx_org, config.influx_token);
+    let store = build_store(&config);
+    if let Some(Command::Import(args)) = config.command {
+        if let Err(err) = run_import(
+            store,
+            args,
+            config.cache_path.as_deref(),
+            config.rollups_path.as_deref(),
+        )
+        .await
+        {
+            eprintln!("Import failed: {}", err);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     let state = Arc::new(ServerState {
-        client,
-        bucket: config.influx_bucket,
+        store,
         coordinates,
+        elevation: config.elevation,
+        rain_day: config.rain_day,
+        ecowitt_passkey: config.ecowitt_passkey,
+        wu_station_id: config.wu_station_id,
+        wu_password: config.wu_password,
+        admin_token: config.admin_token,
+        live: LiveSender::new(None),
+        today: TodaySender::new(None),
+        rollups: Arc::new(DailyRollups::new(config.rollups_path)),
+        cache: Arc::new(RangeCache::new(
+            Duration::seconds(config.cache_ttl_seconds),
+            config.cache_path,
+        )),
     });
+    tokio::spawn(poll_observations(
+        state.clone(),
+        std::time::Duration::from_secs(config.live_poll_seconds),
+    ));
 
     println!("Starting server on {}", binding_address);
     let static_files = ServeDir::new("frontend").fallback(ServeFile::new("frontend/index.html"));
     let router = Router::new()
         .route("/api/past", get(past))
         .route("/api/today", get(today))
+        .route("/api/observations", get(observations))
+        .route("/api/live", get(live_events))
+        .route("/api/live/ws", get(live_socket))
         .route("/api/monthly", get(monthly))
+        .route("/api/yearly", get(yearly))
+        .route("/api/daily", get(daily))
+        .route("/api/records", get(all_records))
+        .route("/api/climate", get(climate))
+        .route("/api/rollups/rebuild", post(rebuild_rollups))
+        .route("/api/export", get(export))
+        .route("/api/windrose", get(windrose))
+        .route("/api/forecast", get(forecast))
+        .route("/data/report", post(ecowitt))
+        .route("/data/report/", post(ecowitt))
+        .route(
+            "/weatherstation/updateweatherstation.php",
+            get(wunderground),
+        )
         .fallback_service(static_files)
         .with_state(state);
     let listener = tokio::net::TcpListener::bind(binding_address)
@@ -0,0 +1,180 @@
+use crate::pressure::observation_sea_level_pressure;
+use crate::rain::{RainDayBoundary, local_utc_offset, rain_since_day_start};
+use crate::resolution::Resolution;
+use crate::thermodynamics::Thermodynamics;
+use crate::types::{HourRecordFlux, ObservationRow};
+use crate::wind::cardinal;
+use crate::{ApiError, ServerState, feels_like_temperature};
+use axum::Json;
+use axum::extract::{Query, State};
+use chrono::{DateTime, Duration, FixedOffset, Local};
+use serde::Deserialize;
+use std::sync::Arc;
+
+const TABLE_HOURS: i64 = 72;
+
+// Each row uses the closest observation within this many minutes of its time
+const ROW_MINUTES: i64 = 5;
+
+#[derive(Deserialize, Debug, Clone, Copy, Default)]
+pub(crate) enum ObservationInterval {
+    #[serde(rename = "10m")]
+    TenMinutes,
+    #[default]
+    #[serde(rename = "30m")]
+    ThirtyMinutes,
+}
+
+impl ObservationInterval {
+    fn duration(&self) -> Duration {
+        match self {
+            ObservationInterval::TenMinutes => Duration::minutes(10),
+            ObservationInterval::ThirtyMinutes => Duration::minutes(30),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub(crate) struct ObservationsParams {
+    #[serde(default)]
+    interval: ObservationInterval,
+}
+
+fn observation_row(
+    state: &ServerState,
+    datum: &HourRecordFlux,
+    rainsince9am: f64,
+) -> ObservationRow {
+    let thermodynamics =
+        Thermodynamics::from_readings(datum.tempc, datum.humidity, datum.windspeedkph);
+    ObservationRow {
+        time: datum.time,
+        tempc: datum.tempc,
+        feelslike: feels_like_temperature(datum.tempc, datum.humidity, datum.windspeedkph),
+        dewpoint: thermodynamics.map(|t| t.dewpoint),
+        humidity: datum.humidity,
+        winddir: datum.winddir,
+        windcardinal: datum.winddir.map(|winddir| cardinal(winddir).to_string()),
+        windspeedkph: datum.windspeedkph,
+        windgustkph: datum.windgustkph,
+        baromslhpa: observation_sea_level_pressure(
+            datum.baromabshpa,
+            datum.baromrelhpa,
+            state.elevation,
+            datum.tempc,
+        ),
+        rainratemm: datum.rainratemm,
+        rainsince9am,
+    }
+}
+
+/// Index of the closest observation in data sorted by time to each multiple of the interval in
+/// local time, newest first
+fn rows(
+    data: &[HourRecordFlux],
+    start: DateTime<FixedOffset>,
+    end: DateTime<FixedOffset>,
+    interval: Duration,
+    utc_offset: Duration,
+) -> Vec<usize> {
+    let seconds = interval.num_seconds();
+    let mut time =
+        end - Duration::seconds((end.timestamp() + utc_offset.num_seconds()).rem_euclid(seconds));
+    let mut result = Vec::new();
+    while time >= start {
+        let after = data.partition_point(|datum| datum.time < time);
+        let closest = [after.checked_sub(1), Some(after)]
+            .into_iter()
+            .flatten()
+            .filter(|index| {
+                data.get(*index).is_some_and(|datum| {
+                    (datum.time - time).abs() <= Duration::minutes(ROW_MINUTES)
+                })
+            })
+            .min_by_key(|index| (data[*index].time - time).abs());
+        if let Some(closest) = closest {
+            result.push(closest);
+        }
+        time -= interval;
+    }
+    result
+}
+
+pub(crate) async fn observations(
+    State(state): State<Arc<ServerState>>,
+    Query(params): Query<ObservationsParams>,
+) -> Result<Json<Vec<ObservationRow>>, ApiError> {
+    let end = Local::now().fixed_offset();
+    let start = end - Duration::hours(TABLE_HOURS);
+    // Rain since 9am in the oldest row counts from the 9am before it
+    let mut data = state
+        .store
+        .range(
+            RainDayBoundary::NineAm.current_start(&start) - Duration::minutes(15),
+            end,
+            Resolution::Raw,
+        )
+        .await?;
+    data.sort_by_key(|r| r.time);
+
+    let rain = rain_since_day_start(&data, RainDayBoundary::NineAm);
+    let interval = params.interval.duration();
+    let result = rows(&data, start, end, interval, local_utc_offset())
+        .into_iter()
+        .map(|index| observation_row(&state, &data[index], rain[index]))
+        .collect();
+    Ok(Json(result))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_align_to_local_time() {
+        let start = DateTime::parse_from_rfc3339("2025-01-01T00:00:00+05:45").unwrap();
+        let data: Vec<HourRecordFlux> = (0..24)
+            .map(|index| HourRecordFlux {
+                time: start + Duration::minutes(index * 5 + 1),
+                ..Default::default()
+            })
+            .collect();
+        let end = start + Duration::minutes(100);
+        let times: Vec<String> = rows(
+            &data,
+            start,
+            end,
+            Duration::minutes(30),
+            Duration::minutes(345),
+        )
+        .into_iter()
+        .map(|index| data[index].time.to_rfc3339())
+        .collect();
+        assert_eq!(
+            times,
+            [
+                "2025-01-01T01:31:00+05:45",
+                "2025-01-01T01:01:00+05:45",
+                "2025-01-01T00:31:00+05:45",
+                "2025-01-01T00:01:00+05:45",
+            ]
+        );
+    }
+
+    #[test]
+    fn rows_skip_gaps() {
+        let start = DateTime::parse_from_rfc3339("2025-01-01T00:00:00Z").unwrap();
+        let data = [HourRecordFlux {
+            time: start + Duration::minutes(26),
+            ..Default::default()
+        }];
+        let rows = rows(
+            &data,
+            start,
+            start + Duration::minutes(60),
+            Duration::minutes(10),
+            Duration::zero(),
+        );
+        assert_eq!(rows, [0]);
+    }
+}
@@ -0,0 +1,156 @@
+use crate::stats::FieldStats;
+use serde::{Deserialize, Serialize};
+
+const LAPSE_RATE: f64 = 0.0065;
+const BAROMETRIC_EXPONENT: f64 = 5.257;
+
+// Changes over three hours smaller than this are reported as steady
+const STEADY_HPA: f64 = 1_f64;
+// Changes over three hours larger than this are reported as rapid
+const RAPID_HPA: f64 = 3.5;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum PressureTendency {
+    RisingRapidly,
+    Rising,
+    Steady,
+    Falling,
+    FallingRapidly,
+}
+
+impl PressureTendency {
+    /// Classifies the change in mean sea-level pressure over the previous three hours
+    pub(crate) fn from_change(change: f64) -> Self {
+        if change > RAPID_HPA {
+            PressureTendency::RisingRapidly
+        } else if change >= STEADY_HPA {
+            PressureTendency::Rising
+        } else if change > -STEADY_HPA {
+            PressureTendency::Steady
+        } else if change >= -RAPID_HPA {
+            PressureTendency::Falling
+        } else {
+            PressureTendency::FallingRapidly
+        }
+    }
+}
+
+/// Reduces station pressure to mean sea-level pressure, using the station temperature to estimate
+/// the temperature of the air column below the station
+pub(crate) fn sea_level_pressure(station_hpa: f64, elevation: f64, tempc: f64) -> f64 {
+    let rise = LAPSE_RATE * elevation;
+    station_hpa * (1_f64 - rise / (tempc + rise + 273.15)).powf(-BAROMETRIC_EXPONENT)
+}
+
+/// Ratio of mean sea-level pressure to station pressure in the standard atmosphere, for
+/// reducing aggregated pressures where individual temperatures are not available
+pub(crate) fn sea_level_factor(elevation: f64) -> f64 {
+    sea_level_pressure(1_f64, elevation, 15_f64 - LAPSE_RATE * elevation)
+}
+
+/// Mean sea-level pressure of an observation, if it reported a pressure. Absolute pressure is only
+/// reduced when the station's elevation is known, otherwise the station's own relative pressure
+/// is preferred. Without a temperature the standard atmosphere is assumed.
+pub(crate) fn observation_sea_level_pressure(
+    baromabshpa: Option<f64>,
+    baromrelhpa: Option<f64>,
+    elevation: Option<f64>,
+    tempc: Option<f64>,
+) -> Option<f64> {
+    let baromabshpa = baromabshpa.filter(|pressure| *pressure > 0_f64);
+    let baromrelhpa = baromrelhpa.filter(|pressure| *pressure > 0_f64);
+    match (baromabshpa, elevation) {
+        (Some(baromabshpa), Some(elevation)) => Some(match tempc {
+            Some(tempc) => sea_level_pressure(baromabshpa, elevation, tempc),
+            None => baromabshpa * sea_level_factor(elevation),
+        }),
+        _ => baromrelhpa.or(baromabshpa),
+    }
+}
+
+/// Mean sea-level pressure over a period, chosen from the absolute and relative pressures the same
+/// way as for a single observation
+pub(crate) fn sea_level_pressure_stats(
+    baromabshpa: FieldStats,
+    baromrelhpa: FieldStats,
+    elevation: Option<f64>,
+) -> FieldStats {
+    match elevation {
+        Some(elevation) if baromabshpa.count > 0 => baromabshpa.scaled(sea_level_factor(elevation)),
+        _ if baromrelhpa.count > 0 => baromrelhpa,
+        _ => baromabshpa,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reduces_to_sea_level() {
+        assert_eq!(sea_level_pressure(1000_f64, 0_f64, 20_f64), 1000_f64);
+        // 100 m raises pressure by about 12 hPa
+        let reduced = sea_level_pressure(1000_f64, 100_f64, 15_f64);
+        assert!((reduced - 1011.9).abs() < 0.1, "{reduced}");
+        // Colder air is denser, so more is added below the station
+        assert!(sea_level_pressure(1000_f64, 100_f64, -10_f64) > reduced);
+        // The standard atmosphere gives 1013.25 hPa at sea level from 954.61 hPa at 500 m
+        let factor = sea_level_factor(500_f64);
+        assert!((954.61 * factor - 1013.25).abs() < 0.1, "{factor}");
+    }
+
+    #[test]
+    fn prefers_relative_pressure_without_elevation() {
+        assert_eq!(
+            observation_sea_level_pressure(Some(950_f64), Some(1010_f64), None, Some(20_f64)),
+            Some(1010_f64)
+        );
+        assert_eq!(
+            observation_sea_level_pressure(Some(950_f64), None, None, Some(20_f64)),
+            Some(950_f64)
+        );
+        assert_eq!(
+            observation_sea_level_pressure(Some(0_f64), Some(0_f64), None, None),
+            None
+        );
+    }
+
+    #[test]
+    fn reduces_absolute_pressure_with_elevation() {
+        assert_eq!(
+            observation_sea_level_pressure(
+                Some(1000_f64),
+                Some(990_f64),
+                Some(100_f64),
+                Some(15_f64)
+            ),
+            Some(sea_level_pressure(1000_f64, 100_f64, 15_f64))
+        );
+        assert_eq!(
+            observation_sea_level_pressure(Some(1000_f64), None, Some(100_f64), None),
+            Some(1000_f64 * sea_level_factor(100_f64))
+        );
+        assert_eq!(
+            observation_sea_level_pressure(None, Some(1012_f64), Some(100_f64), Some(15_f64)),
+            Some(1012_f64)
+        );
+    }
+
+    #[test]
+    fn chooses_daily_pressure() {
+        let mut baromabshpa = FieldStats::default();
+        baromabshpa.add(Some(1000_f64));
+        let mut baromrelhpa = FieldStats::default();
+        baromrelhpa.add(Some(1010_f64));
+
+        let chosen = sea_level_pressure_stats(baromabshpa, baromrelhpa, None);
+        assert_eq!(chosen.max, 1010_f64);
+        let chosen = sea_level_pressure_stats(baromabshpa, baromrelhpa, Some(100_f64));
+        assert_eq!(chosen.max, 1000_f64 * sea_level_factor(100_f64));
+        let chosen = sea_level_pressure_stats(baromabshpa, FieldStats::default(), None);
+        assert_eq!(chosen.max, 1000_f64);
+        let chosen = sea_level_pressure_stats(FieldStats::default(), baromrelhpa, Some(100_f64));
+        assert_eq!(chosen.max, 1010_f64);
+    }
+}
@@ -0,0 +1,547 @@
+use crate::types::HourRecordFlux;
+use chrono::{DateTime, Days, Duration, FixedOffset, Local, NaiveDate, TimeZone};
+use clap::ValueEnum;
+use std::collections::BTreeMap;
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub(crate) enum RainDayBoundary {
+    /// Calendar days in the station's timezone
+    Midnight,
+    /// The 24 hours to 9am, as used for Australian and New Zealand climatology. Rain is counted
+    /// towards the day on which the period ends.
+    #[value(name = "9am")]
+    NineAm,
+}
+
+/// The given hour on a date in the station's timezone
+pub(crate) fn local_time(date: NaiveDate, hour: u32) -> DateTime<FixedOffset> {
+    local_time_in(&Local, date, hour)
+}
+
+/// The given hour on a date in a timezone. An hour that the clocks skip as they go forward, such
+/// as midnight in zones that change at midnight, becomes the first time that exists after it.
+fn local_time_in<Tz: TimeZone>(zone: &Tz, date: NaiveDate, hour: u32) -> DateTime<FixedOffset> {
+    let time = date.and_hms_opt(hour, 0, 0).unwrap();
+    // Gaps are whole minutes, and no zone has skipped more than a day
+    (0..=2 * 24 * 60)
+        .find_map(|minutes| {
+            zone.from_local_datetime(&(time + Duration::minutes(minutes)))
+                .earliest()
+        })
+        .expect("no valid local time within two days")
+        .fixed_offset()
+}
+
+// Rain counters are rejected when they increase faster than this many mm per hour, beyond an
+// allowance for a single observation after a short gap
+const MAX_RAIN_RATE: f64 = 300_f64;
+const MAX_RAIN_ALLOWANCE: f64 = 10_f64;
+
+/// A station's cumulative rain counters at one observation. Stations may also report their own
+/// daily and event totals, which reset themselves and are preferred over the running total.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct RainCounters {
+    pub(crate) time: DateTime<FixedOffset>,
+    pub(crate) totalrainmm: Option<f64>,
+    pub(crate) dailyrainmm: Option<f64>,
+    pub(crate) eventrainmm: Option<f64>,
+}
+
+impl From<&HourRecordFlux> for RainCounters {
+    fn from(record: &HourRecordFlux) -> RainCounters {
+        // Ranges cached before missing counters were read as None have them as -1
+        let reported = |value: Option<f64>| value.filter(|value| *value >= 0_f64);
+        RainCounters {
+            time: record.time,
+            totalrainmm: reported(record.totalrainmm),
+            dailyrainmm: reported(record.dailyrainmm),
+            eventrainmm: reported(record.eventrainmm),
+        }
+    }
+}
+
+/// Rain between two readings of a counter. A decrease means the counter was reset or rolled
+/// over, so everything since is new rain.
+fn counter_increase(previous: f64, next: f64) -> f64 {
+    if next >= previous {
+        next - previous
+    } else {
+        next
+    }
+}
+
+/// Rain that fell between two observations, from the first counter both reported, or 0 if the
+/// increase is implausibly large for the time between them
+pub(crate) fn rain_increment(previous: &RainCounters, next: &RainCounters) -> f64 {
+    let counters = [
+        (previous.dailyrainmm, next.dailyrainmm),
+        (previous.eventrainmm, next.eventrainmm),
+        (previous.totalrainmm, next.totalrainmm),
+    ];
+    let Some(increase) = counters.iter().find_map(|counter| match counter {
+        (Some(previous), Some(next)) => Some(counter_increase(*previous, *next)),
+        _ => None,
+    }) else {
+        return 0_f64;
+    };
+
+    let hours = (next.time - previous.time).num_seconds().max(0) as f64 / 3600_f64;
+    if increase > MAX_RAIN_ALLOWANCE + MAX_RAIN_RATE * hours {
+        return 0_f64;
+    }
+    increase
+}
+
+/// Accumulates rain over a series of observations in time order
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RainAccumulator {
+    last: Option<RainCounters>,
+}
+
+impl RainAccumulator {
+    /// Rain since the previous observation, or None for the first
+    pub(crate) fn add(&mut self, counters: RainCounters) -> Option<f64> {
+        self.last
+            .replace(counters)
+            .map(|previous| rain_increment(&previous, &counters))
+    }
+}
+
+/// Rain for each rain day from counters in time order, where the first only provides the starting
+/// counters
+pub(crate) fn rain_by_day(
+    counters: impl IntoIterator<Item = RainCounters>,
+    rain_day: RainDayBoundary,
+) -> BTreeMap<NaiveDate, f64> {
+    let mut accumulator = RainAccumulator::default();
+    let mut rain = BTreeMap::new();
+    for counters in counters {
+        if let Some(increment) = accumulator.add(counters) {
+            *rain.entry(rain_day.date(&counters.time)).or_default() += increment;
+        }
+    }
+    rain
+}
+
+/// The station's current offset from UTC
+pub(crate) fn local_utc_offset() -> Duration {
+    Duration::seconds(Local::now().offset().local_minus_utc() as i64)
+}
+
+/// Rain between from and to, from observations sorted by time including one before from
+pub(crate) fn rain_between(
+    data: &[HourRecordFlux],
+    from: DateTime<FixedOffset>,
+    to: DateTime<FixedOffset>,
+) -> f64 {
+    data.windows(2)
+        .filter(|pair| pair[1].time > from && pair[1].time <= to)
+        .map(|pair| rain_increment(&(&pair[0]).into(), &(&pair[1]).into()))
+        .fold(0_f64, |total, rain| total + rain)
+}
+
+/// Rain since the start of the rain day at each of a series of observations sorted by time, where
+/// the first only provides the starting counters. Matches rain_between from the start of each
+/// observation's rain day, in a single pass.
+pub(crate) fn rain_since_day_start(data: &[HourRecordFlux], rain_day: RainDayBoundary) -> Vec<f64> {
+    let mut accumulator = RainAccumulator::default();
+    let mut day_start = None;
+    let mut total = 0_f64;
+    data.iter()
+        .map(|datum| {
+            let start = rain_day.current_start(&datum.time);
+            if day_start != Some(start) {
+                day_start = Some(start);
+                total = 0_f64;
+            }
+            let increment = accumulator.add(datum.into()).unwrap_or(0_f64);
+            if datum.time > start {
+                total += increment;
+            }
+            total
+        })
+        .collect()
+}
+
+impl RainDayBoundary {
+    pub(crate) fn hour(&self) -> u32 {
+        match self {
+            RainDayBoundary::Midnight => 0,
+            RainDayBoundary::NineAm => 9,
+        }
+    }
+
+    /// Start of the rain day named by date
+    pub(crate) fn start(&self, date: NaiveDate) -> DateTime<FixedOffset> {
+        match self {
+            RainDayBoundary::Midnight => local_time(date, 0),
+            RainDayBoundary::NineAm => local_time(date - Days::new(1), self.hour()),
+        }
+    }
+
+    /// End of the rain day named by date
+    pub(crate) fn end(&self, date: NaiveDate) -> DateTime<FixedOffset> {
+        self.start(date + Days::new(1))
+    }
+
+    /// Date of the rain day that time falls in
+    pub(crate) fn date<Tz: TimeZone>(&self, time: &DateTime<Tz>) -> NaiveDate {
+        let date = time.with_timezone(&Local).date_naive();
+        if time.with_timezone(&Local).fixed_offset() >= self.start(date + Days::new(1)) {
+            date + Days::new(1)
+        } else {
+            date
+        }
+    }
+
+    /// Start of the rain day that time falls in
+    pub(crate) fn current_start<Tz: TimeZone>(&self, time: &DateTime<Tz>) -> DateTime<FixedOffset> {
+        self.start(self.date(time))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{LocalResult, NaiveDateTime};
+
+    fn counters(minutes: i64, total: f64, daily: Option<f64>, event: Option<f64>) -> RainCounters {
+        RainCounters {
+            time: DateTime::parse_from_rfc3339("2025-01-01T00:00:00Z").unwrap()
+                + Duration::minutes(minutes),
+            totalrainmm: Some(total),
+            dailyrainmm: daily,
+            eventrainmm: event,
+        }
+    }
+
+    /// A zone that goes from UTC-4 to UTC-3 at midnight on 2025-09-07, skipping the first hour
+    /// of the day as Chile does
+    #[derive(Clone, Copy, Debug)]
+    struct SkipsMidnight;
+
+    impl SkipsMidnight {
+        fn change() -> NaiveDateTime {
+            NaiveDate::from_ymd_opt(2025, 9, 7)
+                .unwrap()
+                .and_hms_opt(4, 0, 0)
+                .unwrap()
+        }
+
+        fn offset(hours: i32) -> FixedOffset {
+            FixedOffset::west_opt(hours * 3600).unwrap()
+        }
+    }
+
+    impl TimeZone for SkipsMidnight {
+        type Offset = FixedOffset;
+
+        fn from_offset(_: &FixedOffset) -> Self {
+            SkipsMidnight
+        }
+
+        // Dates take the offset at midday, which is fixed on either side of the change
+        fn offset_from_local_date(&self, local: &NaiveDate) -> LocalResult<FixedOffset> {
+            self.offset_from_local_datetime(&local.and_hms_opt(12, 0, 0).unwrap())
+        }
+
+        fn offset_from_local_datetime(&self, local: &NaiveDateTime) -> LocalResult<FixedOffset> {
+            let change = Self::change() - Duration::hours(4);
+            match *local {
+                local if local < change => LocalResult::Single(Self::offset(4)),
+                local if local < change + Duration::hours(1) => LocalResult::None,
+                _ => LocalResult::Single(Self::offset(3)),
+            }
+        }
+
+        fn offset_from_utc_date(&self, utc: &NaiveDate) -> FixedOffset {
+            self.offset_from_utc_datetime(&utc.and_hms_opt(12, 0, 0).unwrap())
+        }
+
+        fn offset_from_utc_datetime(&self, utc: &NaiveDateTime) -> FixedOffset {
+            match *utc < Self::change() {
+                true => Self::offset(4),
+                false => Self::offset(3),
+            }
+        }
+    }
+
+    #[test]
+    fn skipped_hours_move_forward() {
+        let date = NaiveDate::from_ymd_opt(2025, 9, 7).unwrap();
+        assert_eq!(
+            local_time_in(&SkipsMidnight, date, 0),
+            DateTime::parse_from_rfc3339("2025-09-07T01:00:00-03:00").unwrap()
+        );
+        assert_eq!(
+            local_time_in(&SkipsMidnight, date, 9),
+            DateTime::parse_from_rfc3339("2025-09-07T09:00:00-03:00").unwrap()
+        );
+        assert_eq!(
+            local_time_in(&SkipsMidnight, date.pred_opt().unwrap(), 0),
+            DateTime::parse_from_rfc3339("2025-09-06T00:00:00-04:00").unwrap()
+        );
+        assert_eq!(
+            SkipsMidnight.offset_from_utc_date(&date),
+            SkipsMidnight::offset(3)
+        );
+        assert_eq!(
+            SkipsMidnight.offset_from_local_date(&date.pred_opt().unwrap()),
+            LocalResult::Single(SkipsMidnight::offset(4))
+        );
+    }
+
+    fn accumulate(series: &[RainCounters]) -> f64 {
+        let mut accumulator = RainAccumulator::default();
+        series
+            .iter()
+            .filter_map(|counters| accumulator.add(*counters))
+            .sum()
+    }
+
+    #[test]
+    fn first_observation_has_no_increment() {
+        let mut accumulator = RainAccumulator::default();
+        assert_eq!(accumulator.add(counters(0, 100.0, None, None)), None);
+        assert_eq!(accumulator.add(counters(5, 100.5, None, None)), Some(0.5));
+    }
+
+    #[test]
+    fn steady_increase() {
+        let series = [
+            counters(0, 100.0, None, None),
+            counters(5, 101.0, None, None),
+            counters(10, 103.0, None, None),
+        ];
+        assert_eq!(accumulate(&series), 3.0);
+    }
+
+    #[test]
+    fn reset_to_zero_counts_rain_since_reset() {
+        // Battery swap part way through a shower
+        let series = [
+            counters(0, 250.0, None, None),
+            counters(5, 252.0, None, None),
+            counters(10, 0.0, None, None),
+            counters(15, 1.5, None, None),
+        ];
+        assert_eq!(accumulate(&series), 3.5);
+    }
+
+    #[test]
+    fn reset_with_rain_before_first_reading() {
+        // Yearly reset, with rain between the reset and the next observation
+        let series = [counters(0, 800.0, None, None), counters(5, 0.6, None, None)];
+        assert_eq!(accumulate(&series), 0.6);
+    }
+
+    #[test]
+    fn rollover_counts_as_reset() {
+        let series = [
+            counters(0, 9999.5, None, None),
+            counters(5, 0.3, None, None),
+        ];
+        assert_eq!(accumulate(&series), 0.3);
+    }
+
+    #[test]
+    fn console_reboot_to_stale_total_is_rejected() {
+        // The console comes back with an older total, which isn't rain since a reset
+        let series = [
+            counters(0, 520.0, None, None),
+            counters(5, 480.0, None, None),
+            counters(10, 481.0, None, None),
+        ];
+        assert_eq!(accumulate(&series), 1.0);
+    }
+
+    #[test]
+    fn implausible_jump_is_rejected() {
+        let series = [
+            counters(0, 100.0, None, None),
+            counters(5, 600.0, None, None),
+            counters(10, 601.0, None, None),
+        ];
+        assert_eq!(accumulate(&series), 1.0);
+    }
+
+    #[test]
+    fn large_increase_after_long_gap_is_accepted() {
+        // 120 mm over a six hour outage is heavy but plausible
+        let series = [
+            counters(0, 100.0, None, None),
+            counters(360, 220.0, None, None),
+        ];
+        assert_eq!(accumulate(&series), 120.0);
+    }
+
+    #[test]
+    fn daily_counter_is_preferred() {
+        // The running total jumps on a console reboot, but the daily counter is unaffected
+        let series = [
+            counters(0, 100.0, Some(2.0), None),
+            counters(5, 0.0, Some(2.5), None),
+        ];
+        assert_eq!(accumulate(&series), 0.5);
+    }
+
+    #[test]
+    fn daily_counter_midnight_reset() {
+        let series = [
+            counters(0, 100.0, Some(12.0), None),
+            counters(5, 100.4, Some(0.4), None),
+        ];
+        assert_eq!(accumulate(&series), 0.4);
+    }
+
+    #[test]
+    fn event_counter_used_without_daily() {
+        let series = [
+            counters(0, 0.0, None, Some(4.0)),
+            counters(5, 0.0, None, Some(5.0)),
+        ];
+        assert_eq!(accumulate(&series), 1.0);
+    }
+
+    #[test]
+    fn falls_back_to_total_when_daily_missing_from_one_reading() {
+        let series = [
+            counters(0, 100.0, Some(2.0), None),
+            counters(5, 101.0, None, None),
+        ];
+        assert_eq!(accumulate(&series), 1.0);
+    }
+
+    #[test]
+    fn missing_counters_count_as_no_rain() {
+        let mut previous = counters(0, 0.0, None, None);
+        previous.totalrainmm = None;
+        assert_eq!(
+            rain_increment(&previous, &counters(5, 3.0, None, None)),
+            0.0
+        );
+    }
+
+    #[test]
+    fn counters_from_record_treat_negative_as_missing() {
+        let record = HourRecordFlux {
+            totalrainmm: Some(10.0),
+            dailyrainmm: Some(-1.0),
+            eventrainmm: Some(0.0),
+            ..Default::default()
+        };
+        let counters = RainCounters::from(&record);
+        assert_eq!(counters.totalrainmm, Some(10.0));
+        assert_eq!(counters.dailyrainmm, None);
+        assert_eq!(counters.eventrainmm, Some(0.0));
+        assert_eq!(
+            RainCounters::from(&HourRecordFlux::default()).totalrainmm,
+            None
+        );
+    }
+
+    #[test]
+    fn rain_between_handles_reset() {
+        let start = DateTime::parse_from_rfc3339("2025-01-01T00:00:00Z").unwrap();
+        let record = |minutes: i64, total: f64| HourRecordFlux {
+            time: start + Duration::minutes(minutes),
+            totalrainmm: Some(total),
+            ..Default::default()
+        };
+        let data = [
+            record(0, 50.0),
+            record(5, 51.0),
+            record(10, 0.0),
+            record(15, 0.5),
+            record(20, 2.0),
+        ];
+        assert_eq!(
+            rain_between(&data, start, start + Duration::minutes(15)),
+            1.5
+        );
+        assert_eq!(
+            rain_between(
+                &data,
+                start + Duration::minutes(5),
+                start + Duration::minutes(20)
+            ),
+            2.0
+        );
+    }
+
+    #[test]
+    fn rain_since_day_start_matches_rain_between() {
+        let date = NaiveDate::from_ymd_opt(2025, 1, 10).unwrap();
+        let data: Vec<HourRecordFlux> = [(7, 10.0), (8, 11.0), (9, 13.0), (10, 14.0), (11, 0.5)]
+            .into_iter()
+            .map(|(hour, total)| HourRecordFlux {
+                time: local_time(date, hour),
+                totalrainmm: Some(total),
+                ..Default::default()
+            })
+            .collect();
+        let since = rain_since_day_start(&data, RainDayBoundary::NineAm);
+        assert_eq!(since, [0.0, 1.0, 0.0, 1.0, 1.5]);
+        for (datum, since) in data.iter().zip(since) {
+            let start = RainDayBoundary::NineAm.current_start(&datum.time);
+            assert_eq!(rain_between(&data, start, datum.time), since);
+        }
+    }
+
+    fn local_counters(date: NaiveDate, hour: u32, total: f64, daily: Option<f64>) -> RainCounters {
+        RainCounters {
+            time: local_time(date, hour),
+            totalrainmm: Some(total),
+            dailyrainmm: daily,
+            eventrainmm: None,
+        }
+    }
+
+    #[test]
+    fn rain_by_day_keeps_rain_after_reset() {
+        let first = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+        let second = NaiveDate::from_ymd_opt(2025, 1, 2).unwrap();
+        let rain = rain_by_day(
+            [
+                local_counters(first, 12, 5.0, None),
+                local_counters(first, 18, 7.0, None),
+                // Counter reset overnight, so all of it is new rain
+                local_counters(second, 6, 1.0, None),
+                local_counters(second, 12, 3.0, None),
+            ],
+            RainDayBoundary::Midnight,
+        );
+        assert_eq!(rain.get(&first), Some(&2.0));
+        assert_eq!(rain.get(&second), Some(&3.0));
+    }
+
+    #[test]
+    fn rain_by_day_prefers_daily_counter_and_ignores_jumps() {
+        let date = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+        let rain = rain_by_day(
+            [
+                local_counters(date, 10, 100.0, Some(0.0)),
+                local_counters(date, 11, 100.0, Some(2.0)),
+                local_counters(date, 12, 600.0, None),
+                local_counters(date, 13, 601.0, None),
+            ],
+            RainDayBoundary::Midnight,
+        );
+        assert_eq!(rain.get(&date), Some(&3.0));
+    }
+
+    #[test]
+    fn rain_by_day_splits_at_9am() {
+        let date = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+        let rain = rain_by_day(
+            [
+                local_counters(date, 7, 10.0, None),
+                local_counters(date, 8, 11.0, None),
+                local_counters(date, 10, 14.0, None),
+            ],
+            RainDayBoundary::NineAm,
+        );
+        assert_eq!(rain.get(&date), Some(&1.0));
+        assert_eq!(rain.get(&(date + Days::new(1))), Some(&3.0));
+    }
+}
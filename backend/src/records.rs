@@ -0,0 +1,157 @@
+use crate::stats::{DaySummary, FieldStats};
+use crate::types::{MonthRecordSet, NewRecord, RecordScope, RecordSet, Records, WeatherRecord};
+use crate::{ApiError, ServerState};
+use axum::Json;
+use axum::extract::State;
+use chrono::{Datelike, NaiveDate};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+fn higher(record: &mut Option<WeatherRecord>, value: f64, date: NaiveDate) {
+    // Ties keep the earlier date, as that is when the record was first set
+    if record.is_none_or(|record| value > record.value) {
+        *record = Some(WeatherRecord { value, date });
+    }
+}
+
+fn lower(record: &mut Option<WeatherRecord>, value: f64, date: NaiveDate) {
+    if record.is_none_or(|record| value < record.value) {
+        *record = Some(WeatherRecord { value, date });
+    }
+}
+
+fn reported(stats: &FieldStats) -> Option<&FieldStats> {
+    (stats.count > 0).then_some(stats)
+}
+
+impl RecordSet {
+    fn add_day(&mut self, day: &DaySummary) {
+        if let Some(tempc) = reported(&day.tempc) {
+            higher(&mut self.highesttemp, tempc.max, day.date);
+            lower(&mut self.lowesttemp, tempc.min, day.date);
+            higher(&mut self.highestmintemp, tempc.min, day.date);
+            lower(&mut self.lowestmaxtemp, tempc.max, day.date);
+        }
+        if let Some(windgustkph) = reported(&day.windgustkph) {
+            higher(&mut self.strongestgust, windgustkph.max, day.date);
+        }
+        if let Some(uv) = reported(&day.uv) {
+            higher(&mut self.highestuv, uv.max, day.date);
+        }
+        if day.rainmm > 0_f64 {
+            higher(&mut self.wettestday, day.rainmm, day.date);
+        }
+    }
+}
+
+/// All-time and calendar month records from daily summaries, which must be sorted by date
+pub(crate) fn records(days: &[DaySummary]) -> Records {
+    let mut alltime = RecordSet::default();
+    let mut months: Vec<RecordSet> = vec![RecordSet::default(); 12];
+    let mut rain_days: BTreeMap<NaiveDate, u32> = BTreeMap::new();
+    for day in days {
+        alltime.add_day(day);
+        months[day.date.month0() as usize].add_day(day);
+        let month_start = day.date.with_day(1).unwrap();
+        let count = rain_days.entry(month_start).or_default();
+        if day.rainmm > 0_f64 {
+            *count += 1;
+        }
+    }
+    for (month_start, count) in rain_days {
+        higher(&mut alltime.mostraindays, count as f64, month_start);
+        higher(
+            &mut months[month_start.month0() as usize].mostraindays,
+            count as f64,
+            month_start,
+        );
+    }
+
+    Records {
+        alltime,
+        months: months
+            .into_iter()
+            .enumerate()
+            .map(|(index, records)| MonthRecordSet {
+                month: index as u32 + 1,
+                records,
+            })
+            .collect(),
+    }
+}
+
+fn beaten(
+    name: &str,
+    scope: RecordScope,
+    record: Option<WeatherRecord>,
+    value: Option<f64>,
+    highest: bool,
+) -> Option<NewRecord> {
+    let (previous, value) = (record?, value?);
+    let beaten = if highest {
+        value > previous.value
+    } else {
+        value < previous.value
+    };
+    beaten.then(|| NewRecord {
+        record: name.to_string(),
+        scope,
+        value,
+        previous,
+    })
+}
+
+/// Records beaten by today's values so far. The highest minimum and lowest maximum aren't
+/// settled until the day is over, so are not checked.
+pub(crate) fn new_records(records: &Records, today: &DaySummary, rainmm: f64) -> Vec<NewRecord> {
+    let tempc = reported(&today.tempc);
+    let windgustkph = reported(&today.windgustkph);
+    let uv = reported(&today.uv);
+    let month = &records.months[today.date.month0() as usize].records;
+    [
+        (RecordScope::AllTime, &records.alltime),
+        (RecordScope::Month, month),
+    ]
+    .into_iter()
+    .flat_map(|(scope, set)| {
+        [
+            beaten(
+                "highesttemp",
+                scope,
+                set.highesttemp,
+                tempc.map(|t| t.max),
+                true,
+            ),
+            beaten(
+                "lowesttemp",
+                scope,
+                set.lowesttemp,
+                tempc.map(|t| t.min),
+                false,
+            ),
+            beaten(
+                "strongestgust",
+                scope,
+                set.strongestgust,
+                windgustkph.map(|g| g.max),
+                true,
+            ),
+            beaten("wettestday", scope, set.wettestday, Some(rainmm), true),
+            beaten("highestuv", scope, set.highestuv, uv.map(|uv| uv.max), true),
+        ]
+    })
+    .flatten()
+    .collect()
+}
+
+/// Summaries of every day up to now
+pub(crate) async fn all_day_summaries(state: &ServerState) -> Result<Vec<DaySummary>, ApiError> {
+    state.rollups.all(state).await
+}
+
+pub(crate) async fn all_records(
+    State(state): State<Arc<ServerState>>,
+) -> Result<Json<Records>, ApiError> {
+    let days = all_day_summaries(&state).await?;
+    Ok(Json(records(&days)))
+}
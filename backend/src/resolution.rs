@@ -0,0 +1,68 @@
+use chrono::Duration;
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub(crate) enum Resolution {
+    #[serde(rename = "raw")]
+    Raw,
+    #[serde(rename = "5m")]
+    FiveMinutes,
+    #[serde(rename = "10m")]
+    TenMinutes,
+    #[serde(rename = "30m")]
+    ThirtyMinutes,
+    #[default]
+    #[serde(rename = "1h")]
+    Hour,
+    #[serde(rename = "1d")]
+    Day,
+}
+
+impl Resolution {
+    /// Width of each aggregation window, or None for unaggregated observations
+    pub(crate) fn seconds(&self) -> Option<i64> {
+        match self {
+            Resolution::Raw => None,
+            Resolution::FiveMinutes => Some(300),
+            Resolution::TenMinutes => Some(600),
+            Resolution::ThirtyMinutes => Some(1800),
+            Resolution::Hour => Some(3600),
+            Resolution::Day => Some(86400),
+        }
+    }
+
+    /// Window duration as a Flux/InfluxQL duration literal
+    pub(crate) fn duration_literal(&self) -> Option<&'static str> {
+        match self {
+            Resolution::Raw => None,
+            Resolution::FiveMinutes => Some("5m"),
+            Resolution::TenMinutes => Some("10m"),
+            Resolution::ThirtyMinutes => Some("30m"),
+            Resolution::Hour => Some("1h"),
+            Resolution::Day => Some("1d"),
+        }
+    }
+
+    /// Offset of the windows from the epoch so that they start on the local hour or day, using
+    /// the given UTC offset
+    pub(crate) fn window_offset(&self, utc_offset: Duration) -> Option<Duration> {
+        self.seconds()
+            .map(|window| Duration::seconds((-utc_offset).num_seconds().rem_euclid(window)))
+    }
+
+    /// The finest resolution that keeps a range to a few hundred points, for charts that don't
+    /// ask for one
+    pub(crate) fn for_range(range: Duration) -> Resolution {
+        if range <= Duration::days(1) {
+            Resolution::FiveMinutes
+        } else if range <= Duration::days(3) {
+            Resolution::TenMinutes
+        } else if range <= Duration::days(7) {
+            Resolution::ThirtyMinutes
+        } else if range <= Duration::days(31) {
+            Resolution::Hour
+        } else {
+            Resolution::Day
+        }
+    }
+}
@@ -0,0 +1,371 @@
+use crate::ApiError::Unavailable;
+use crate::rain::local_time;
+use crate::stats::DaySummary;
+use crate::{ApiError, ServerState};
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode, header};
+use chrono::{DateTime, Datelike, Days, Duration, FixedOffset, Local, NaiveDate, Utc};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::{Mutex, RwLock};
+
+// Days this recent are read again from the store on each refresh, as they may still change. Rain
+// for a day ending at 9am continues into the next, and needs an earlier observation to start from.
+const REFRESH_DAYS: u64 = 2;
+
+// A failed build is retried after this long, doubling with each failure up to the maximum
+const RETRY_MINUTES: i64 = 1;
+const MAX_RETRY_MINUTES: i64 = 60;
+
+// A rebuild reads every year from this one, as the store can't say when the station started
+const FIRST_YEAR: i32 = 1970;
+
+type RollupDays = BTreeMap<NaiveDate, DaySummary>;
+
+#[derive(Default)]
+struct Updates {
+    failures: u32,
+    retry_at: Option<DateTime<Utc>>,
+    /// The last day that was past the refresh window when the days were saved
+    saved_through: Option<NaiveDate>,
+}
+
+/// Summaries of every day the station has recorded, from which the monthly, yearly and record
+/// statistics are worked out without going back to the store. Loaded from the saved copy or built
+/// on first use, then kept up to date by refresh.
+pub(crate) struct DailyRollups {
+    days: RwLock<Option<RollupDays>>,
+    /// Held while reading from the store, so that a refresh and a rebuild can't overwrite each
+    /// other's days
+    updates: Mutex<Updates>,
+    path: Option<PathBuf>,
+}
+
+impl DailyRollups {
+    pub(crate) fn new(path: Option<PathBuf>) -> Self {
+        let days = path.as_deref().and_then(load);
+        Self {
+            days: RwLock::new(days),
+            updates: Mutex::new(Updates::default()),
+            path,
+        }
+    }
+
+    /// Reads the days from first to last inclusive, a year at a time so that a long history isn't
+    /// read in one query
+    async fn read_days(
+        state: &ServerState,
+        first: NaiveDate,
+        last: NaiveDate,
+    ) -> Result<Vec<DaySummary>, ApiError> {
+        let mut days = Vec::new();
+        let mut chunk_first = first;
+        while chunk_first <= last {
+            let chunk_last = NaiveDate::from_ymd_opt(chunk_first.year(), 12, 31)
+                .unwrap()
+                .min(last);
+            // Starting the day before gives rain on the first day an observation to start from
+            let chunk = state
+                .store
+                .day_summaries(
+                    state.rain_day.start(chunk_first - Days::new(1)),
+                    local_time(chunk_last + Days::new(1), 0),
+                    state.elevation,
+                    state.rain_day,
+                )
+                .await?;
+            days.extend(
+                chunk
+                    .into_iter()
+                    .filter(|day| (chunk_first..=chunk_last).contains(&day.date)),
+            );
+            chunk_first = chunk_last + Days::new(1);
+        }
+        Ok(days)
+    }
+
+    /// Reads every day from the store again, e.g. after importing history
+    pub(crate) async fn rebuild(&self, state: &ServerState) -> Result<(), ApiError> {
+        let mut updates = self.updates.lock().await;
+        self.rebuild_locked(state, &mut updates).await
+    }
+
+    async fn rebuild_locked(
+        &self,
+        state: &ServerState,
+        updates: &mut Updates,
+    ) -> Result<(), ApiError> {
+        let first = NaiveDate::from_ymd_opt(FIRST_YEAR, 1, 1).unwrap();
+        let days = match Self::read_days(state, first, Local::now().date_naive()).await {
+            Ok(days) => days,
+            Err(err) => {
+                let backoff = (RETRY_MINUTES << updates.failures.min(16)).min(MAX_RETRY_MINUTES);
+                updates.failures += 1;
+                updates.retry_at = Some(Utc::now() + Duration::minutes(backoff));
+                return Err(err);
+            }
+        };
+        updates.failures = 0;
+        updates.retry_at = None;
+        *self.days.write().await = Some(days.into_iter().map(|day| (day.date, day)).collect());
+        self.save(updates).await;
+        Ok(())
+    }
+
+    /// Builds the days if they haven't been, unless an earlier attempt failed recently
+    async fn build_locked(
+        &self,
+        state: &ServerState,
+        updates: &mut Updates,
+    ) -> Result<(), ApiError> {
+        if self.days.read().await.is_some() {
+            return Ok(());
+        }
+        if let Some(retry_at) = updates.retry_at
+            && retry_at > Utc::now()
+        {
+            return Err(Unavailable(format!(
+                "Daily rollups failed to build, retrying at {}",
+                retry_at.to_rfc3339()
+            )));
+        }
+        self.rebuild_locked(state, updates).await
+    }
+
+    /// Reads the days that may have changed since the last refresh, or since the newest saved day
+    pub(crate) async fn refresh(&self, state: &ServerState) -> Result<(), ApiError> {
+        let mut updates = self.updates.lock().await;
+        if self.days.read().await.is_none() {
+            return self.build_locked(state, &mut updates).await;
+        }
+        let today = Local::now().date_naive();
+        let newest = self
+            .days
+            .read()
+            .await
+            .as_ref()
+            .and_then(|days| days.keys().next_back().copied());
+        let first = newest
+            .map_or(today, |newest| newest - Days::new(1))
+            .min(today - Days::new(REFRESH_DAYS));
+        let recent = Self::read_days(state, first + Days::new(1), today).await?;
+        if let Some(days) = self.days.write().await.as_mut() {
+            days.extend(recent.into_iter().map(|day| (day.date, day)));
+        }
+        if updates.saved_through < Some(today - Days::new(REFRESH_DAYS)) {
+            self.save(&mut updates).await;
+        }
+        Ok(())
+    }
+
+    /// Reads again the days kom import has marked as stale, if any
+    async fn update_stale(&self, state: &ServerState) -> Result<(), ApiError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let (marked, reading) = stale_paths(path);
+        if !exists(&marked).await && !exists(&reading).await {
+            return Ok(());
+        }
+
+        let mut updates = self.updates.lock().await;
+        // Moved aside while the days are read, so that any an import marks meanwhile aren't lost.
+        // Left there if reading fails, to be tried again.
+        if !exists(&reading).await {
+            match tokio::fs::rename(&marked, &reading).await {
+                Ok(()) => {}
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+                Err(err) => return Err(ApiError::Other(format!("{}: {}", marked.display(), err))),
+            }
+        }
+        let dates = match read_stale(&reading).await {
+            Ok(dates) => dates,
+            Err(err) => {
+                eprintln!("Ignoring unreadable stale days: {}", err);
+                BTreeSet::new()
+            }
+        };
+        for (first, last) in runs(&dates) {
+            let days = Self::read_days(state, first, last).await?;
+            if let Some(saved) = self.days.write().await.as_mut() {
+                saved.extend(days.into_iter().map(|day| (day.date, day)));
+            }
+        }
+        self.save(&mut updates).await;
+        let _ = tokio::fs::remove_file(&reading).await;
+        Ok(())
+    }
+
+    /// Writes the days to disk, if a path was given
+    async fn save(&self, updates: &mut Updates) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let json = match self.days.read().await.as_ref() {
+            Some(days) => serde_json::to_vec(&days.values().collect::<Vec<_>>()).unwrap(),
+            None => return,
+        };
+        match write_file(path, json).await {
+            Ok(()) => {
+                updates.saved_through = Some(Local::now().date_naive() - Days::new(REFRESH_DAYS))
+            }
+            Err(err) => eprintln!("Saving daily rollups failed: {}", err),
+        }
+    }
+
+    /// Summaries of the days from first to last inclusive, sorted by date
+    pub(crate) async fn between(
+        &self,
+        state: &ServerState,
+        first: NaiveDate,
+        last: NaiveDate,
+    ) -> Result<Vec<DaySummary>, ApiError> {
+        if self.days.read().await.is_none() {
+            let mut updates = self.updates.lock().await;
+            self.build_locked(state, &mut updates).await?;
+        }
+        self.update_stale(state).await?;
+        let days = self.days.read().await;
+        Ok(days
+            .iter()
+            .flat_map(|days| days.range(first..=last))
+            .map(|(_, day)| day.clone())
+            .collect())
+    }
+
+    /// Summaries of the local days covering a range, sorted by date
+    pub(crate) async fn range(
+        &self,
+        state: &ServerState,
+        start: DateTime<FixedOffset>,
+        end: DateTime<FixedOffset>,
+    ) -> Result<Vec<DaySummary>, ApiError> {
+        let first = start.with_timezone(&Local).date_naive();
+        let last = (end - Duration::seconds(1))
+            .with_timezone(&Local)
+            .date_naive();
+        self.between(state, first, last).await
+    }
+
+    pub(crate) async fn all(&self, state: &ServerState) -> Result<Vec<DaySummary>, ApiError> {
+        self.between(state, NaiveDate::MIN, NaiveDate::MAX).await
+    }
+}
+
+/// Written alongside and renamed, so a crash part way through doesn't leave a partial file
+async fn write_file(path: &Path, contents: Vec<u8>) -> std::io::Result<()> {
+    let temporary = with_suffix(path, ".tmp");
+    tokio::fs::write(&temporary, contents).await?;
+    tokio::fs::rename(&temporary, path).await
+}
+
+async fn exists(path: &Path) -> bool {
+    tokio::fs::try_exists(path).await.unwrap_or(false)
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+/// Files listing the days marked as stale for the rollups saved at path, and those being read again
+fn stale_paths(path: &Path) -> (PathBuf, PathBuf) {
+    (with_suffix(path, ".stale"), with_suffix(path, ".reading"))
+}
+
+async fn read_stale(path: &Path) -> std::io::Result<BTreeSet<NaiveDate>> {
+    match tokio::fs::read(path).await {
+        Ok(json) => Ok(serde_json::from_slice(&json)?),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(BTreeSet::new()),
+        Err(err) => Err(err),
+    }
+}
+
+/// Runs of consecutive dates, as first and last inclusive
+fn runs(dates: &BTreeSet<NaiveDate>) -> Vec<(NaiveDate, NaiveDate)> {
+    let mut runs: Vec<(NaiveDate, NaiveDate)> = Vec::new();
+    for date in dates {
+        match runs.last_mut() {
+            Some((_, last)) if *last + Days::new(1) == *date => *last = *date,
+            _ => runs.push((*date, *date)),
+        }
+    }
+    runs
+}
+
+/// Marks the days from first to last inclusive as needing to be read again by a server saving its
+/// rollups at path, for writes made outside the server. The server notices the next time it reads
+/// them.
+pub(crate) async fn mark_stale(
+    path: &Path,
+    first: NaiveDate,
+    last: NaiveDate,
+) -> std::io::Result<()> {
+    let (marked, _) = stale_paths(path);
+    let mut dates = read_stale(&marked).await?;
+    dates.extend(first.iter_days().take_while(|date| *date <= last));
+    write_file(&marked, serde_json::to_vec(&dates).unwrap()).await
+}
+
+fn load(path: &Path) -> Option<RollupDays> {
+    let json = std::fs::read(path).ok()?;
+    let days: Vec<DaySummary> = serde_json::from_slice(&json)
+        .inspect_err(|err| eprintln!("Ignoring unreadable daily rollups: {}", err))
+        .ok()?;
+    Some(days.into_iter().map(|day| (day.date, day)).collect())
+}
+
+/// Reads every day from the store again, for a bearer ADMIN_TOKEN
+pub(crate) async fn rebuild_rollups(
+    State(state): State<Arc<ServerState>>,
+    headers: HeaderMap,
+) -> Result<StatusCode, ApiError> {
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match (&state.admin_token, token) {
+        (Some(expected), Some(token)) if expected == token => {}
+        _ => return Err(ApiError::Unauthorised),
+    }
+    state.rollups.rebuild(&state).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 1, day).unwrap()
+    }
+
+    #[test]
+    fn groups_consecutive_dates() {
+        let dates = BTreeSet::from([date(1), date(2), date(3), date(5), date(7), date(8)]);
+        assert_eq!(
+            runs(&dates),
+            [(date(1), date(3)), (date(5), date(5)), (date(7), date(8))]
+        );
+        assert!(runs(&BTreeSet::new()).is_empty());
+    }
+
+    #[tokio::test]
+    async fn marks_stale_days() {
+        let directory = std::env::temp_dir().join(format!("kom-rollups-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("rollups.json");
+
+        mark_stale(&path, date(1), date(3)).await.unwrap();
+        mark_stale(&path, date(3), date(4)).await.unwrap();
+        let (marked, reading) = stale_paths(&path);
+        assert_eq!(reading, directory.join("rollups.json.reading"));
+        let dates = read_stale(&marked).await.unwrap();
+        assert_eq!(runs(&dates), [(date(1), date(4))]);
+        assert!(!exists(&path).await);
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,205 @@
+use chrono::{DateTime, Datelike, Duration, FixedOffset, Timelike};
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+use sunrise::Coordinates;
+
+// Below this clear-sky irradiance (W/m²) the sun is too low for the measured radiation to say
+// anything about cloud, so the sky is reported as night
+const NIGHT_RADIATION: f64 = 10_f64;
+
+// Estimated cloud cover fractions separating sunny, partly cloudy and cloudy skies
+const SUNNY_CLOUD_COVER: f64 = 0.3;
+const CLOUDY_CLOUD_COVER: f64 = 0.7;
+
+// Interval between solar positions when averaging clear-sky irradiance over a window
+const SAMPLE_MINUTES: i64 = 5;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Sky {
+    Sunny,
+    PartlyCloudy,
+    Cloudy,
+    Night,
+    Rain,
+    Showers,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SkyConditions {
+    pub(crate) clearskyradiation: f64,
+    pub(crate) clearnessindex: Option<f64>,
+    /// Estimated fraction of the sky covered by cloud, from 0 to 1
+    pub(crate) cloudcover: Option<f64>,
+    /// None in daylight without a radiation reading
+    pub(crate) sky: Option<Sky>,
+}
+
+/// Cosine of the solar zenith angle, using the NOAA approximations for the equation of time and
+/// solar declination
+fn cos_zenith(time: DateTime<FixedOffset>, coordinates: &Coordinates) -> f64 {
+    let time = time.to_utc();
+    let hour = time.hour() as f64 + time.minute() as f64 / 60_f64 + time.second() as f64 / 3600_f64;
+    let gamma = 2_f64 * PI / 365_f64 * (time.ordinal0() as f64 + (hour - 12_f64) / 24_f64);
+
+    let equation_of_time = 229.18
+        * (0.000075 + 0.001868 * gamma.cos()
+            - 0.032077 * gamma.sin()
+            - 0.014615 * (2_f64 * gamma).cos()
+            - 0.040849 * (2_f64 * gamma).sin());
+    let declination = 0.006918 - 0.399912 * gamma.cos() + 0.070257 * gamma.sin()
+        - 0.006758 * (2_f64 * gamma).cos()
+        + 0.000907 * (2_f64 * gamma).sin()
+        - 0.002697 * (3_f64 * gamma).cos()
+        + 0.00148 * (3_f64 * gamma).sin();
+
+    let solar_minutes = hour * 60_f64 + equation_of_time + 4_f64 * coordinates.lon();
+    let hour_angle = (solar_minutes / 4_f64 - 180_f64).to_radians();
+    let latitude = coordinates.lat().to_radians();
+
+    latitude.sin() * declination.sin() + latitude.cos() * declination.cos() * hour_angle.cos()
+}
+
+/// Global horizontal irradiance (W/m²) under a cloudless sky, from the Haurwitz model
+pub(crate) fn clear_sky_radiation(time: DateTime<FixedOffset>, coordinates: &Coordinates) -> f64 {
+    let cos_zenith = cos_zenith(time, coordinates);
+    if cos_zenith <= 0_f64 {
+        return 0_f64;
+    }
+    1098_f64 * cos_zenith * (-0.057 / cos_zenith).exp()
+}
+
+/// Mean clear-sky irradiance over the window ending at end, matching the averaged measurements
+pub(crate) fn mean_clear_sky_radiation(
+    end: DateTime<FixedOffset>,
+    window: Duration,
+    coordinates: &Coordinates,
+) -> f64 {
+    let samples = (window.num_minutes() / SAMPLE_MINUTES).max(1);
+    let step = window / samples as i32;
+    (0..samples)
+        .map(|sample| clear_sky_radiation(end - step * sample as i32 - step / 2, coordinates))
+        .sum::<f64>()
+        / samples as f64
+}
+
+/// Classifies the sky from measured and clear-sky irradiance. Cloud cover is estimated by inverting
+/// the Kasten-Czeplak relation between cloud cover and the clearness index. Without a radiation
+/// reading only night and rain can be told apart.
+pub(crate) fn classify(
+    solarradiation: Option<f64>,
+    clearskyradiation: f64,
+    raining: bool,
+) -> SkyConditions {
+    let solarradiation = match solarradiation {
+        Some(solarradiation) if clearskyradiation >= NIGHT_RADIATION => solarradiation,
+        _ => {
+            let night = clearskyradiation < NIGHT_RADIATION;
+            return SkyConditions {
+                clearskyradiation,
+                clearnessindex: None,
+                cloudcover: None,
+                sky: match (raining, night) {
+                    (true, _) => Some(Sky::Rain),
+                    (false, true) => Some(Sky::Night),
+                    (false, false) => None,
+                },
+            };
+        }
+    };
+
+    let clearnessindex = (solarradiation.max(0_f64) / clearskyradiation).min(1_f64);
+    let cloudcover = ((1_f64 - clearnessindex) / 0.75)
+        .powf(1_f64 / 3.4)
+        .min(1_f64);
+    let sky = match (raining, cloudcover) {
+        (true, cover) if cover < CLOUDY_CLOUD_COVER => Sky::Showers,
+        (true, _) => Sky::Rain,
+        (false, cover) if cover < SUNNY_CLOUD_COVER => Sky::Sunny,
+        (false, cover) if cover < CLOUDY_CLOUD_COVER => Sky::PartlyCloudy,
+        (false, _) => Sky::Cloudy,
+    };
+
+    SkyConditions {
+        clearskyradiation,
+        clearnessindex: Some(clearnessindex),
+        cloudcover: Some(cloudcover),
+        sky: Some(sky),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(value: &str) -> DateTime<FixedOffset> {
+        DateTime::parse_from_rfc3339(value).unwrap()
+    }
+
+    fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "{actual} != {expected}"
+        );
+    }
+
+    #[test]
+    fn solar_zenith_known_values() {
+        // At the June solstice the sun is overhead at solar noon on the Tropic of Cancer, which
+        // the equation of time puts at 12:02 UTC on the prime meridian
+        let tropic = Coordinates::new(23.44, 0_f64).unwrap();
+        let cos = cos_zenith(time("2025-06-21T12:02:00Z"), &tropic);
+        assert_close(cos, 1_f64, 1e-4);
+        // and 21.56° from overhead at 45°N
+        let north = Coordinates::new(45_f64, 0_f64).unwrap();
+        let cos = cos_zenith(time("2025-06-21T12:02:00Z"), &north);
+        assert_close(cos.acos().to_degrees(), 21.56, 0.2);
+        // Sydney at the December solstice: 33.87° - 23.44° = 10.43° from overhead at solar noon
+        let sydney = Coordinates::new(-33.87, 151.2).unwrap();
+        let cos = cos_zenith(time("2025-12-21T12:53:00+11:00"), &sydney);
+        assert_close(cos.acos().to_degrees(), 10.43, 0.2);
+        assert!(cos_zenith(time("2025-12-21T00:00:00+11:00"), &sydney) < 0_f64);
+    }
+
+    #[test]
+    fn clear_sky_radiation_known_values() {
+        // Haurwitz: 1098 exp(-0.057) with the sun overhead
+        let tropic = Coordinates::new(23.44, 0_f64).unwrap();
+        let overhead = clear_sky_radiation(time("2025-06-21T12:02:00Z"), &tropic);
+        assert_close(overhead, 1037.2, 1_f64);
+        // and 1098 cos(60°) exp(-0.057 / cos(60°)) with the sun 60° from overhead
+        let south = Coordinates::new(-36.56, 0_f64).unwrap();
+        let radiation = clear_sky_radiation(time("2025-06-21T12:02:00Z"), &south);
+        assert_close(radiation, 489.8, 3_f64);
+        let midnight = clear_sky_radiation(time("2025-06-21T00:00:00Z"), &tropic);
+        assert_eq!(midnight, 0_f64);
+    }
+
+    #[test]
+    fn classifies_cloud_cover() {
+        let sunny = classify(Some(990_f64), 1000_f64, false);
+        assert_eq!(sunny.sky, Some(Sky::Sunny));
+        assert_close(sunny.cloudcover.unwrap(), 0.28, 0.01);
+        let partly = classify(Some(900_f64), 1000_f64, false);
+        assert_eq!(partly.sky, Some(Sky::PartlyCloudy));
+        assert_close(partly.cloudcover.unwrap(), 0.55, 0.01);
+        let cloudy = classify(Some(200_f64), 1000_f64, false);
+        assert_eq!(cloudy.sky, Some(Sky::Cloudy));
+        assert_eq!(cloudy.cloudcover, Some(1_f64));
+        assert_eq!(
+            classify(Some(900_f64), 1000_f64, true).sky,
+            Some(Sky::Showers)
+        );
+        assert_eq!(classify(Some(200_f64), 1000_f64, true).sky, Some(Sky::Rain));
+    }
+
+    #[test]
+    fn classifies_without_radiation() {
+        assert_eq!(classify(Some(0_f64), 5_f64, false).sky, Some(Sky::Night));
+        assert_eq!(classify(None, 5_f64, false).sky, Some(Sky::Night));
+        assert_eq!(classify(None, 500_f64, true).sky, Some(Sky::Rain));
+        let unknown = classify(None, 500_f64, false);
+        assert_eq!(unknown.sky, None);
+        assert_eq!(unknown.cloudcover, None);
+    }
+}
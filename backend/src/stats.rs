@@ -0,0 +1,297 @@
+use crate::types::MonthRecordFlux;
+use chrono::{Datelike, NaiveDate, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub(crate) struct FieldStats {
+    pub(crate) min: f64,
+    pub(crate) max: f64,
+    pub(crate) sum: f64,
+    pub(crate) count: u64,
+}
+
+impl Default for FieldStats {
+    fn default() -> Self {
+        Self {
+            min: f64::MAX,
+            max: f64::MIN,
+            sum: 0_f64,
+            count: 0_u64,
+        }
+    }
+}
+
+impl FieldStats {
+    pub(crate) fn add(&mut self, value: Option<f64>) {
+        if let Some(value) = value {
+            self.min = self.min.min(value);
+            self.max = self.max.max(value);
+            self.sum += value;
+            self.count += 1;
+        }
+    }
+
+    pub(crate) fn merge(&mut self, other: &FieldStats) {
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        self.sum += other.sum;
+        self.count += other.count;
+    }
+
+    pub(crate) fn min_or_zero(&self) -> f64 {
+        if self.count == 0 {
+            return 0_f64;
+        }
+        self.min
+    }
+
+    pub(crate) fn max_or_zero(&self) -> f64 {
+        if self.count == 0 {
+            return 0_f64;
+        }
+        self.max
+    }
+
+    pub(crate) fn min_value(&self) -> Option<f64> {
+        (self.count > 0).then_some(self.min)
+    }
+
+    pub(crate) fn max_value(&self) -> Option<f64> {
+        (self.count > 0).then_some(self.max)
+    }
+
+    /// Multiplies every value by a positive factor, e.g. to reduce pressure to sea level
+    pub(crate) fn scaled(&self, factor: f64) -> FieldStats {
+        if self.count == 0 {
+            return *self;
+        }
+        FieldStats {
+            min: self.min * factor,
+            max: self.max * factor,
+            sum: self.sum * factor,
+            count: self.count,
+        }
+    }
+
+    pub(crate) fn mean(&self) -> f64 {
+        if self.count == 0 {
+            return 0_f64;
+        }
+        self.sum / self.count as f64
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct DaySummary {
+    pub(crate) date: NaiveDate,
+    pub(crate) tempc: FieldStats,
+    pub(crate) tempinc: FieldStats,
+    pub(crate) humidity: FieldStats,
+    pub(crate) humidityin: FieldStats,
+    pub(crate) uv: FieldStats,
+    pub(crate) solarradiation: FieldStats,
+    pub(crate) baromslhpa: FieldStats,
+    pub(crate) windgustkph: FieldStats,
+    pub(crate) rainmm: f64,
+}
+
+impl DaySummary {
+    pub(crate) fn new(date: NaiveDate) -> Self {
+        Self {
+            date,
+            tempc: FieldStats::default(),
+            tempinc: FieldStats::default(),
+            humidity: FieldStats::default(),
+            humidityin: FieldStats::default(),
+            uv: FieldStats::default(),
+            solarradiation: FieldStats::default(),
+            baromslhpa: FieldStats::default(),
+            windgustkph: FieldStats::default(),
+            rainmm: 0_f64,
+        }
+    }
+
+    /// Whether any observations were made on the day, rather than it only having rain carried
+    /// over from the previous day's counters
+    pub(crate) fn recorded(&self) -> bool {
+        [
+            &self.tempc,
+            &self.tempinc,
+            &self.humidity,
+            &self.humidityin,
+            &self.uv,
+            &self.solarradiation,
+            &self.baromslhpa,
+            &self.windgustkph,
+        ]
+        .iter()
+        .any(|stats| stats.count > 0)
+    }
+}
+
+/// Sets the rain of each day from totals by rain day, which don't necessarily line up with the days
+/// of the other statistics, adding days that only have rain. Days must be sorted by date.
+pub(crate) fn add_rain(days: &mut Vec<DaySummary>, rain: BTreeMap<NaiveDate, f64>) {
+    for (date, rainmm) in rain {
+        match days.binary_search_by_key(&date, |day| day.date) {
+            Ok(index) => days[index].rainmm = rainmm,
+            Err(index) => {
+                let mut day = DaySummary::new(date);
+                day.rainmm = rainmm;
+                days.insert(index, day);
+            }
+        }
+    }
+}
+
+// Mean of the daily extremes, ignoring days where the field was never reported
+#[derive(Default)]
+struct MeanOfExtremes {
+    max_sum: f64,
+    min_sum: f64,
+    count: u64,
+}
+
+impl MeanOfExtremes {
+    fn add(&mut self, day: &FieldStats) {
+        if day.count > 0 {
+            self.max_sum += day.max;
+            self.min_sum += day.min;
+            self.count += 1;
+        }
+    }
+
+    fn mean_max(&self) -> f64 {
+        if self.count == 0 {
+            return 0_f64;
+        }
+        self.max_sum / self.count as f64
+    }
+
+    fn mean_min(&self) -> f64 {
+        if self.count == 0 {
+            return 0_f64;
+        }
+        self.min_sum / self.count as f64
+    }
+}
+
+#[derive(Default)]
+struct MonthAccumulator {
+    tempc: (FieldStats, MeanOfExtremes),
+    tempinc: (FieldStats, MeanOfExtremes),
+    humidity: (FieldStats, MeanOfExtremes),
+    humidityin: (FieldStats, MeanOfExtremes),
+    uv: (FieldStats, MeanOfExtremes),
+    solarradiation: (FieldStats, MeanOfExtremes),
+    baromslhpa: (FieldStats, MeanOfExtremes),
+    totalrainmm: f64,
+    raindayscount: i64,
+    dayscount: i64,
+}
+
+impl MonthAccumulator {
+    fn add(&mut self, day: &DaySummary) {
+        for (acc, stats) in [
+            (&mut self.tempc, &day.tempc),
+            (&mut self.tempinc, &day.tempinc),
+            (&mut self.humidity, &day.humidity),
+            (&mut self.humidityin, &day.humidityin),
+            (&mut self.uv, &day.uv),
+            (&mut self.solarradiation, &day.solarradiation),
+            (&mut self.baromslhpa, &day.baromslhpa),
+        ] {
+            acc.0.merge(stats);
+            acc.1.add(stats);
+        }
+        self.totalrainmm += day.rainmm;
+        if day.rainmm > 0_f64 {
+            self.raindayscount += 1;
+        }
+        if day.recorded() {
+            self.dayscount += 1;
+        }
+    }
+
+    fn into_record(self, start: NaiveDate) -> MonthRecordFlux {
+        MonthRecordFlux {
+            time: Utc
+                .from_utc_datetime(&start.and_hms_opt(0, 0, 0).unwrap())
+                .fixed_offset(),
+            humidity: self.humidity.0.mean(),
+            humidity_absolute_max: self.humidity.0.max_or_zero(),
+            humidity_absolute_min: self.humidity.0.min_or_zero(),
+            humidity_mean_max: self.humidity.1.mean_max(),
+            humidity_mean_min: self.humidity.1.mean_min(),
+            humidityin: self.humidityin.0.mean(),
+            humidityin_absolute_max: self.humidityin.0.max_or_zero(),
+            humidityin_absolute_min: self.humidityin.0.min_or_zero(),
+            humidityin_mean_max: self.humidityin.1.mean_max(),
+            humidityin_mean_min: self.humidityin.1.mean_min(),
+            solarradiation: self.solarradiation.0.mean(),
+            solarradiation_absolute_max: self.solarradiation.0.max_or_zero(),
+            solarradiation_absolute_min: self.solarradiation.0.min_or_zero(),
+            solarradiation_mean_max: self.solarradiation.1.mean_max(),
+            solarradiation_mean_min: self.solarradiation.1.mean_min(),
+            tempc: self.tempc.0.mean(),
+            tempc_absolute_max: self.tempc.0.max_or_zero(),
+            tempc_absolute_min: self.tempc.0.min_or_zero(),
+            tempc_mean_max: self.tempc.1.mean_max(),
+            tempc_mean_min: self.tempc.1.mean_min(),
+            tempinc: self.tempinc.0.mean(),
+            tempinc_absolute_max: self.tempinc.0.max_or_zero(),
+            tempinc_absolute_min: self.tempinc.0.min_or_zero(),
+            tempinc_mean_max: self.tempinc.1.mean_max(),
+            tempinc_mean_min: self.tempinc.1.mean_min(),
+            totalrainmm: self.totalrainmm,
+            raindayscount: self.raindayscount,
+            dayscount: self.dayscount,
+            uv_absolute: self.uv.0.max_or_zero(),
+            uv_mean: self.uv.1.mean_max(),
+            baromslhpa: self.baromslhpa.0.mean(),
+            baromslhpa_absolute_max: self.baromslhpa.0.max_or_zero(),
+            baromslhpa_absolute_min: self.baromslhpa.0.min_or_zero(),
+            baromslhpa_mean_max: self.baromslhpa.1.mean_max(),
+            baromslhpa_mean_min: self.baromslhpa.1.mean_min(),
+        }
+    }
+}
+
+/// Statistics for each calendar month from daily summaries, which must be sorted by date
+pub(crate) fn month_records(days: &[DaySummary]) -> Vec<MonthRecordFlux> {
+    period_records(days, |date| date.with_day(1).unwrap())
+}
+
+/// The month statistics over each calendar year, from daily summaries sorted by date
+pub(crate) fn year_records(days: &[DaySummary]) -> Vec<MonthRecordFlux> {
+    period_records(days, |date| date.with_ordinal(1).unwrap())
+}
+
+fn period_records(
+    days: &[DaySummary],
+    period_start: impl Fn(NaiveDate) -> NaiveDate,
+) -> Vec<MonthRecordFlux> {
+    let mut result = Vec::new();
+    let mut current: Option<(NaiveDate, MonthAccumulator)> = None;
+
+    for day in days {
+        let start = period_start(day.date);
+        match &mut current {
+            Some((current_start, acc)) if *current_start == start => acc.add(day),
+            _ => {
+                if let Some((current_start, acc)) = current.take() {
+                    result.push(acc.into_record(current_start));
+                }
+                let mut acc = MonthAccumulator::default();
+                acc.add(day);
+                current = Some((start, acc));
+            }
+        }
+    }
+    if let Some((start, acc)) = current {
+        result.push(acc.into_record(start));
+    }
+
+    result
+}
@@ -0,0 +1,109 @@
+use crate::ApiError;
+use crate::flux::{
+    build_day_summaries_flux, build_latest_flux, build_observation_times_flux,
+    build_rain_counters_flux, build_range_flux, query_flux, query_flux_day_summaries,
+    query_flux_rain_counters,
+};
+use crate::rain::{RainDayBoundary, local_utc_offset, rain_by_day};
+use crate::resolution::Resolution;
+use crate::stats::{DaySummary, add_rain};
+use crate::store::{WeatherStore, data_point};
+use crate::types::{HourRecordFlux, Observation};
+use async_trait::async_trait;
+use chrono::{DateTime, FixedOffset};
+use influxdb2::Client;
+use influxdb2::models::Query as InfluxQuery;
+use influxdb2_structmap::value::Value;
+
+pub(crate) struct InfluxStore {
+    client: Client,
+    bucket: String,
+    measurement: String,
+}
+
+impl InfluxStore {
+    pub(crate) fn new(client: Client, bucket: String, measurement: String) -> Self {
+        Self {
+            client,
+            bucket,
+            measurement,
+        }
+    }
+}
+
+#[async_trait]
+impl WeatherStore for InfluxStore {
+    async fn range(
+        &self,
+        start: DateTime<FixedOffset>,
+        end: DateTime<FixedOffset>,
+        resolution: Resolution,
+    ) -> Result<Vec<HourRecordFlux>, ApiError> {
+        let flux = build_range_flux(
+            &self.bucket,
+            &self.measurement,
+            &start,
+            &end,
+            resolution,
+            local_utc_offset(),
+        );
+        query_flux(&self.client, &flux).await
+    }
+
+    async fn day_summaries(
+        &self,
+        start: DateTime<FixedOffset>,
+        end: DateTime<FixedOffset>,
+        elevation: Option<f64>,
+        rain_day: RainDayBoundary,
+    ) -> Result<Vec<DaySummary>, ApiError> {
+        let flux = build_day_summaries_flux(
+            &self.bucket,
+            &self.measurement,
+            &start,
+            &end,
+            local_utc_offset(),
+        );
+        let mut days = query_flux_day_summaries(&self.client, &flux, elevation).await?;
+        days.sort_by_key(|day| day.date);
+        let flux = build_rain_counters_flux(&self.bucket, &self.measurement, &start, &end);
+        let counters = query_flux_rain_counters(&self.client, &flux).await?;
+        add_rain(&mut days, rain_by_day(counters, rain_day));
+        Ok(days)
+    }
+
+    async fn latest(&self) -> Result<Option<HourRecordFlux>, ApiError> {
+        let flux = build_latest_flux(&self.bucket, &self.measurement);
+        let data = query_flux(&self.client, &flux).await?;
+        Ok(data.into_iter().max_by_key(|r| r.time))
+    }
+
+    async fn observation_times(
+        &self,
+        start: DateTime<FixedOffset>,
+        end: DateTime<FixedOffset>,
+    ) -> Result<Vec<DateTime<FixedOffset>>, ApiError> {
+        let flux = build_observation_times_flux(&self.bucket, &self.measurement, &start, &end);
+        let records = self.client.query_raw(Some(InfluxQuery::new(flux))).await?;
+        Ok(records
+            .iter()
+            .filter_map(|record| match record.values.get("_time") {
+                Some(Value::TimeRFC(time)) => Some(*time),
+                _ => None,
+            })
+            .collect())
+    }
+
+    async fn write(&self, observations: Vec<Observation>) -> Result<(), ApiError> {
+        let points = observations
+            .iter()
+            .map(|observation| data_point(&self.measurement, observation))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        self.client
+            .write(&self.bucket, futures::stream::iter(points))
+            .await?;
+
+        Ok(())
+    }
+}
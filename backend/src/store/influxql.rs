@@ -0,0 +1,534 @@
+use crate::ApiError;
+use crate::pressure::sea_level_pressure_stats;
+use crate::rain::{RainCounters, RainDayBoundary, local_utc_offset, rain_by_day};
+use crate::resolution::Resolution;
+use crate::stats::{DaySummary, FieldStats, add_rain};
+use crate::store::{LATEST_WITHIN, WeatherStore, data_point};
+use crate::types::{HourRecordFlux, Observation};
+use crate::wind::window_vector_mean;
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, FixedOffset, SecondsFormat};
+use influxdb2::models::WriteDataPoint;
+use serde_json::Value;
+use std::collections::HashMap;
+
+const HOURLY_FIELDS: [&str; 15] = [
+    "tempc",
+    "tempinc",
+    "humidity",
+    "humidityin",
+    "windspeedkph",
+    "windgustkph",
+    "winddir",
+    "rainratemm",
+    "totalrainmm",
+    "dailyrainmm",
+    "eventrainmm",
+    "uv",
+    "solarradiation",
+    "baromrelhpa",
+    "baromabshpa",
+];
+
+// Fields aggregated with MAX instead of MEAN: rain counters, as resets make the mean of a counter
+// misleading, and gusts and UV, which are peaks
+const MAX_FIELDS: [&str; 5] = [
+    "totalrainmm",
+    "dailyrainmm",
+    "eventrainmm",
+    "windgustkph",
+    "uv",
+];
+
+const DAILY_FIELDS: [&str; 9] = [
+    "tempc",
+    "tempinc",
+    "humidity",
+    "humidityin",
+    "uv",
+    "solarradiation",
+    "baromrelhpa",
+    "baromabshpa",
+    "windgustkph",
+];
+
+type SeriesRow = HashMap<String, Value>;
+
+/// Reads and writes InfluxDB 1.x databases using InfluxQL, for servers without Flux enabled
+pub(crate) struct InfluxQlStore {
+    client: reqwest::Client,
+    url: String,
+    database: String,
+    retention_policy: Option<String>,
+    measurement: String,
+    username: Option<String>,
+    password: Option<String>,
+}
+
+impl InfluxQlStore {
+    pub(crate) fn new(
+        url: String,
+        database: String,
+        retention_policy: Option<String>,
+        measurement: String,
+        username: Option<String>,
+        password: Option<String>,
+    ) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url: url.trim_end_matches('/').to_string(),
+            database,
+            retention_policy,
+            measurement,
+            username,
+            password,
+        }
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let mut query = vec![("db", self.database.as_str())];
+        if let Some(retention_policy) = &self.retention_policy {
+            query.push(("rp", retention_policy.as_str()));
+        }
+
+        let request = self
+            .client
+            .request(method, format!("{}{}", self.url, path))
+            .query(&query);
+        match &self.username {
+            Some(username) => request.basic_auth(username, self.password.as_ref()),
+            None => request,
+        }
+    }
+
+    fn measurement_clause(&self) -> String {
+        match &self.retention_policy {
+            Some(retention_policy) => format!(
+                "{}.{}",
+                quote_identifier(retention_policy),
+                quote_identifier(&self.measurement)
+            ),
+            None => quote_identifier(&self.measurement),
+        }
+    }
+
+    async fn query(&self, influxql: &str) -> Result<Vec<SeriesRow>, ApiError> {
+        let response = self
+            .request(reqwest::Method::GET, "/query")
+            .query(&[("q", influxql), ("epoch", "s")])
+            .send()
+            .await
+            .map_err(|err| ApiError::InfluxQl(err.to_string()))?;
+        let status = response.status();
+        let body: Value = response
+            .json()
+            .await
+            .map_err(|err| ApiError::InfluxQl(err.to_string()))?;
+        if !status.is_success() {
+            return Err(ApiError::InfluxQl(body.to_string()));
+        }
+
+        parse_series(&body)
+    }
+
+    /// Statistics for each day, starting utc_offset before midnight UTC
+    async fn query_day_summaries(
+        &self,
+        start: DateTime<FixedOffset>,
+        end: DateTime<FixedOffset>,
+        elevation: Option<f64>,
+        rain_day: RainDayBoundary,
+        utc_offset: Duration,
+    ) -> Result<Vec<DaySummary>, ApiError> {
+        let selections = DAILY_FIELDS
+            .iter()
+            .map(|field| {
+                let quoted = quote_identifier(field);
+                format!(
+                    "MIN({quoted}) AS \"{field}_min\", MAX({quoted}) AS \"{field}_max\", \
+                     SUM({quoted}) AS \"{field}_sum\", COUNT({quoted}) AS \"{field}_count\""
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        let range = format!(
+            "time >= {} AND time < {}",
+            time_literal(start),
+            time_literal(end)
+        );
+        let daily_influxql = format!(
+            "SELECT {selections} FROM {} WHERE {range} GROUP BY time(1d, {}s) fill(none)",
+            self.measurement_clause(),
+            (-utc_offset).num_seconds().rem_euclid(86400)
+        );
+        let rain_influxql = format!(
+            "SELECT \"totalrainmm\", \"dailyrainmm\", \"eventrainmm\" FROM {} WHERE {range} ORDER BY time",
+            self.measurement_clause()
+        );
+        let counters = self
+            .query(&rain_influxql)
+            .await?
+            .iter()
+            .map(|row| RainCounters {
+                time: row_time(row),
+                totalrainmm: row_value(row, "totalrainmm"),
+                dailyrainmm: row_value(row, "dailyrainmm"),
+                eventrainmm: row_value(row, "eventrainmm"),
+            })
+            .collect::<Vec<_>>();
+
+        let mut days: Vec<DaySummary> = self
+            .query(&daily_influxql)
+            .await?
+            .iter()
+            .map(|row| {
+                let mut day = DaySummary::new((row_time(row) + utc_offset).date_naive());
+                day.tempc = field_stats_from_row(row, "tempc");
+                day.tempinc = field_stats_from_row(row, "tempinc");
+                day.humidity = field_stats_from_row(row, "humidity");
+                day.humidityin = field_stats_from_row(row, "humidityin");
+                day.uv = field_stats_from_row(row, "uv");
+                day.solarradiation = field_stats_from_row(row, "solarradiation");
+                day.windgustkph = field_stats_from_row(row, "windgustkph");
+                day.baromslhpa = sea_level_pressure_stats(
+                    field_stats_from_row(row, "baromabshpa"),
+                    field_stats_from_row(row, "baromrelhpa"),
+                    elevation,
+                );
+                day
+            })
+            .collect();
+        days.sort_by_key(|day| day.date);
+        add_rain(&mut days, rain_by_day(counters, rain_day));
+
+        Ok(days)
+    }
+}
+
+fn quote_identifier(identifier: &str) -> String {
+    format!(
+        "\"{}\"",
+        identifier.replace('\\', "\\\\").replace('"', "\\\"")
+    )
+}
+
+fn time_literal(time: DateTime<FixedOffset>) -> String {
+    format!(
+        "'{}'",
+        time.to_utc().to_rfc3339_opts(SecondsFormat::Secs, true)
+    )
+}
+
+fn parse_series(body: &Value) -> Result<Vec<SeriesRow>, ApiError> {
+    let mut rows = Vec::new();
+    for result in body["results"].as_array().into_iter().flatten() {
+        if let Some(error) = result["error"].as_str() {
+            return Err(ApiError::InfluxQl(error.to_string()));
+        }
+        for series in result["series"].as_array().into_iter().flatten() {
+            let columns: Vec<&str> = series["columns"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(Value::as_str)
+                .collect();
+            for values in series["values"].as_array().into_iter().flatten() {
+                let row = columns
+                    .iter()
+                    .zip(values.as_array().into_iter().flatten())
+                    .map(|(column, value)| (column.to_string(), value.clone()))
+                    .collect();
+                rows.push(row);
+            }
+        }
+    }
+    Ok(rows)
+}
+
+fn row_time(row: &SeriesRow) -> DateTime<FixedOffset> {
+    let timestamp = row.get("time").and_then(Value::as_i64).unwrap_or_default();
+    DateTime::from_timestamp(timestamp, 0_u32)
+        .unwrap()
+        .fixed_offset()
+}
+
+fn row_value(row: &SeriesRow, column: &str) -> Option<f64> {
+    row.get(column).and_then(Value::as_f64)
+}
+
+fn hour_record_from_row(row: &SeriesRow, time: DateTime<FixedOffset>) -> HourRecordFlux {
+    let value = |column: &str| row_value(row, column);
+    HourRecordFlux {
+        time,
+        tempc: value("tempc"),
+        tempinc: value("tempinc"),
+        humidity: value("humidity"),
+        humidityin: value("humidityin"),
+        windspeedkph: value("windspeedkph"),
+        windgustkph: value("windgustkph"),
+        winddir: value("winddir"),
+        windsteadiness: 1_f64,
+        rainratemm: value("rainratemm"),
+        totalrainmm: value("totalrainmm"),
+        dailyrainmm: value("dailyrainmm"),
+        eventrainmm: value("eventrainmm"),
+        uv: value("uv"),
+        solarradiation: value("solarradiation"),
+        baromrelhpa: value("baromrelhpa"),
+        baromabshpa: value("baromabshpa"),
+    }
+}
+
+fn field_stats_from_row(row: &SeriesRow, field: &str) -> FieldStats {
+    let count = row_value(row, &format!("{field}_count")).unwrap_or(0_f64) as u64;
+    if count == 0 {
+        return FieldStats::default();
+    }
+    FieldStats {
+        min: row_value(row, &format!("{field}_min")).unwrap_or(f64::MAX),
+        max: row_value(row, &format!("{field}_max")).unwrap_or(f64::MIN),
+        sum: row_value(row, &format!("{field}_sum")).unwrap_or(0_f64),
+        count,
+    }
+}
+
+#[async_trait]
+impl WeatherStore for InfluxQlStore {
+    async fn range(
+        &self,
+        start: DateTime<FixedOffset>,
+        end: DateTime<FixedOffset>,
+        resolution: Resolution,
+    ) -> Result<Vec<HourRecordFlux>, ApiError> {
+        let group_by_time = resolution.duration_literal().map(|every| {
+            let offset = resolution
+                .window_offset(local_utc_offset())
+                .unwrap_or_default();
+            format!(
+                "GROUP BY time({every}, {}s) fill(none)",
+                offset.num_seconds()
+            )
+        });
+        let (selections, group_by) = match &group_by_time {
+            Some(group_by_time) => (
+                HOURLY_FIELDS
+                    .iter()
+                    .map(|field| {
+                        let function = if MAX_FIELDS.contains(field) {
+                            "MAX"
+                        } else {
+                            "MEAN"
+                        };
+                        format!("{function}({0}) AS {0}", quote_identifier(field))
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                format!(" {group_by_time}"),
+            ),
+            None => (
+                HOURLY_FIELDS
+                    .iter()
+                    .map(|field| quote_identifier(field))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                "".to_string(),
+            ),
+        };
+        let influxql = format!(
+            "SELECT {selections} FROM {} WHERE time >= {} AND time < {}{group_by}",
+            self.measurement_clause(),
+            time_literal(start),
+            time_literal(end)
+        );
+
+        // Wind direction is averaged as a vector, from the mean of its speed weighted components
+        let wind: HashMap<i64, (Option<f64>, Option<f64>)> = match &group_by_time {
+            Some(group_by_time) => {
+                let range = format!(
+                    "time >= {} AND time < {}",
+                    time_literal(start),
+                    time_literal(end)
+                );
+                let wind_influxql = format!(
+                    "SELECT MEAN(\"windu\") AS \"windu\", MEAN(\"windv\") AS \"windv\" FROM (SELECT \"windspeedkph\" * SIN(\"winddir\" * {radians}) AS \"windu\", \"windspeedkph\" * COS(\"winddir\" * {radians}) AS \"windv\" FROM {} WHERE {range}) WHERE {range} {group_by_time}",
+                    self.measurement_clause(),
+                    radians = std::f64::consts::PI / 180_f64,
+                );
+                self.query(&wind_influxql)
+                    .await?
+                    .iter()
+                    .map(|row| {
+                        (
+                            row_time(row).timestamp(),
+                            (row_value(row, "windu"), row_value(row, "windv")),
+                        )
+                    })
+                    .collect()
+            }
+            None => HashMap::new(),
+        };
+
+        // InfluxQL labels each window with its start, whereas the Flux queries label it with
+        // its end (clamped to the end of the range)
+        let window = Duration::seconds(resolution.seconds().unwrap_or(0));
+        let rows = self.query(&influxql).await?;
+        Ok(rows
+            .iter()
+            .map(|row| {
+                let mut record = hour_record_from_row(row, (row_time(row) + window).min(end));
+                if let Some((u, v)) = wind.get(&row_time(row).timestamp()) {
+                    (record.winddir, record.windsteadiness) =
+                        window_vector_mean(*u, *v, record.windspeedkph);
+                }
+                record
+            })
+            .collect())
+    }
+
+    async fn day_summaries(
+        &self,
+        start: DateTime<FixedOffset>,
+        end: DateTime<FixedOffset>,
+        elevation: Option<f64>,
+        rain_day: RainDayBoundary,
+    ) -> Result<Vec<DaySummary>, ApiError> {
+        self.query_day_summaries(start, end, elevation, rain_day, local_utc_offset())
+            .await
+    }
+
+    async fn latest(&self) -> Result<Option<HourRecordFlux>, ApiError> {
+        let influxql = format!(
+            "SELECT * FROM {} WHERE time > now() - {}s ORDER BY time DESC LIMIT 1",
+            self.measurement_clause(),
+            LATEST_WITHIN.num_seconds()
+        );
+        let rows = self.query(&influxql).await?;
+        Ok(rows
+            .first()
+            .map(|row| hour_record_from_row(row, row_time(row))))
+    }
+
+    async fn observation_times(
+        &self,
+        start: DateTime<FixedOffset>,
+        end: DateTime<FixedOffset>,
+    ) -> Result<Vec<DateTime<FixedOffset>>, ApiError> {
+        // Only the time is needed, so a single field is read rather than every column. The outdoor
+        // temperature is in every Ecowitt and WU upload.
+        let influxql = format!(
+            "SELECT \"tempc\" FROM {} WHERE time >= {} AND time < {}",
+            self.measurement_clause(),
+            time_literal(start),
+            time_literal(end)
+        );
+        let rows = self.query(&influxql).await?;
+        Ok(rows.iter().map(row_time).collect())
+    }
+
+    async fn write(&self, observations: Vec<Observation>) -> Result<(), ApiError> {
+        let mut body = Vec::new();
+        for observation in &observations {
+            data_point(&self.measurement, observation)?
+                .write_data_point_to(&mut body)
+                .map_err(|err| ApiError::Other(err.to_string()))?;
+        }
+
+        let response = self
+            .request(reqwest::Method::POST, "/write")
+            .body(body)
+            .send()
+            .await
+            .map_err(|err| ApiError::InfluxQl(err.to_string()))?;
+        if !response.status().is_success() {
+            let text = response.text().await.unwrap_or_default();
+            return Err(ApiError::InfluxQl(text));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn quotes_identifiers() {
+        assert_eq!(quote_identifier("weather"), "\"weather\"");
+        assert_eq!(quote_identifier("a \"b\""), "\"a \\\"b\\\"\"");
+        assert_eq!(quote_identifier("a\\b"), "\"a\\\\b\"");
+    }
+
+    #[test]
+    fn parses_series() {
+        let body = json!({
+            "results": [{
+                "statement_id": 0,
+                "series": [{
+                    "name": "observations",
+                    "columns": ["time", "tempc", "humidity"],
+                    "values": [[1736000000, 21.5, null], [1736000060, 21.6, 55]]
+                }]
+            }, {
+                "statement_id": 1
+            }]
+        });
+        let rows = parse_series(&body).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(row_time(&rows[0]).timestamp(), 1736000000);
+        assert_eq!(row_value(&rows[0], "tempc"), Some(21.5));
+        assert_eq!(row_value(&rows[0], "humidity"), None);
+        assert_eq!(row_value(&rows[1], "humidity"), Some(55_f64));
+    }
+
+    #[test]
+    fn reports_query_errors() {
+        let body = json!({
+            "results": [{"statement_id": 0, "error": "database not found: weather"}]
+        });
+        match parse_series(&body) {
+            Err(ApiError::InfluxQl(error)) => assert_eq!(error, "database not found: weather"),
+            other => panic!("unexpected {other:?}"),
+        }
+    }
+
+    #[test]
+    fn reads_missing_fields_as_none() {
+        let body = json!({
+            "results": [{
+                "series": [{
+                    "columns": ["time", "tempc", "winddir", "baromrelhpa"],
+                    "values": [[1736000000, 21.5, null, 1013.2]]
+                }]
+            }]
+        });
+        let rows = parse_series(&body).unwrap();
+        let record = hour_record_from_row(&rows[0], row_time(&rows[0]));
+        assert_eq!(record.time.timestamp(), 1736000000);
+        assert_eq!(record.tempc, Some(21.5));
+        assert_eq!(record.baromrelhpa, Some(1013.2));
+        assert_eq!(record.winddir, None);
+        assert_eq!(record.humidity, None);
+        assert_eq!(record.totalrainmm, None);
+    }
+
+    #[test]
+    fn reads_day_statistics() {
+        let row: SeriesRow = [
+            ("tempc_min", json!(10.5)),
+            ("tempc_max", json!(20.5)),
+            ("tempc_sum", json!(62_f64)),
+            ("tempc_count", json!(4)),
+            ("uv_count", json!(0)),
+        ]
+        .into_iter()
+        .map(|(column, value)| (column.to_string(), value))
+        .collect();
+        let tempc = field_stats_from_row(&row, "tempc");
+        assert_eq!((tempc.min, tempc.max, tempc.count), (10.5, 20.5, 4));
+        assert_eq!(tempc.mean(), 15.5);
+        assert_eq!(field_stats_from_row(&row, "uv").count, 0);
+        assert_eq!(field_stats_from_row(&row, "humidity").count, 0);
+    }
+}
@@ -0,0 +1,69 @@
+mod influx;
+mod influxql;
+mod sqlite;
+
+pub(crate) use influx::InfluxStore;
+pub(crate) use influxql::InfluxQlStore;
+pub(crate) use sqlite::SqliteStore;
+
+use crate::ApiError;
+use crate::rain::RainDayBoundary;
+use crate::resolution::Resolution;
+use crate::stats::DaySummary;
+use crate::types::{HourRecordFlux, Observation};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, FixedOffset};
+use influxdb2::models::DataPoint;
+
+/// How recent the latest observation has to be. Kept short, as it is read on every live poll.
+pub(crate) const LATEST_WITHIN: Duration = Duration::hours(1);
+
+#[async_trait]
+pub(crate) trait WeatherStore: Send + Sync {
+    /// Means of each field between start and end at the given resolution, timestamped at the end
+    /// of each window
+    async fn range(
+        &self,
+        start: DateTime<FixedOffset>,
+        end: DateTime<FixedOffset>,
+        resolution: Resolution,
+    ) -> Result<Vec<HourRecordFlux>, ApiError>;
+
+    /// Statistics for each day between start and end in the station's timezone, with pressures
+    /// reduced to sea level from a station at the given elevation in metres, if known, and rain
+    /// totalled over rain days ending at the given boundary
+    async fn day_summaries(
+        &self,
+        start: DateTime<FixedOffset>,
+        end: DateTime<FixedOffset>,
+        elevation: Option<f64>,
+        rain_day: RainDayBoundary,
+    ) -> Result<Vec<DaySummary>, ApiError>;
+
+    /// The most recent observation received within LATEST_WITHIN
+    async fn latest(&self) -> Result<Option<HourRecordFlux>, ApiError>;
+
+    /// Timestamps of every stored observation between start and end
+    async fn observation_times(
+        &self,
+        start: DateTime<FixedOffset>,
+        end: DateTime<FixedOffset>,
+    ) -> Result<Vec<DateTime<FixedOffset>>, ApiError>;
+
+    async fn write(&self, observations: Vec<Observation>) -> Result<(), ApiError>;
+}
+
+// submitted_by and model match the tags written by ecowitt_listener, which the Flux queries
+// expect to exist
+fn data_point(measurement: &str, observation: &Observation) -> Result<DataPoint, ApiError> {
+    let mut builder = DataPoint::builder(measurement)
+        .tag("submitted_by", "kom")
+        .tag("model", observation.model.as_str())
+        .timestamp(observation.time.timestamp_nanos_opt().unwrap_or_default());
+    for (name, value) in observation.fields() {
+        builder = builder.field(name, value);
+    }
+    builder
+        .build()
+        .map_err(|err| ApiError::Other(err.to_string()))
+}
@@ -0,0 +1,377 @@
+use crate::ApiError;
+use crate::pressure::observation_sea_level_pressure;
+use crate::rain::{RainCounters, RainDayBoundary, local_utc_offset, rain_by_day};
+use crate::resolution::Resolution;
+use crate::stats::{DaySummary, add_rain};
+use crate::store::{LATEST_WITHIN, WeatherStore};
+use crate::types::{HourRecordFlux, Observation};
+use crate::wind::{components, window_vector_mean};
+use async_trait::async_trait;
+use chrono::{DateTime, FixedOffset, Local, Utc};
+use rusqlite::functions::FunctionFlags;
+use rusqlite::{Connection, Row, params};
+use std::sync::{Arc, Mutex};
+
+const SCHEMA: &str = r#"CREATE TABLE IF NOT EXISTS observations (
+    time INTEGER PRIMARY KEY,
+    model TEXT NOT NULL,
+    tempc REAL,
+    tempinc REAL,
+    humidity REAL,
+    humidityin REAL,
+    windspeedkph REAL,
+    windgustkph REAL,
+    winddir REAL,
+    rainratemm REAL,
+    totalrainmm REAL,
+    dailyrainmm REAL,
+    eventrainmm REAL,
+    uv REAL,
+    solarradiation REAL,
+    baromrelhpa REAL,
+    baromabshpa REAL
+)"#;
+
+// Missing readings are left as NULL, to be read as None
+const RAW_COLUMNS: &str = r#"tempc, tempinc, humidity, humidityin, windspeedkph, windgustkph, winddir,
+rainratemm, totalrainmm, uv, solarradiation, baromrelhpa, baromabshpa, dailyrainmm, eventrainmm"#;
+
+// winddir is replaced by the mean wind components, which are converted back to a direction in
+// window_record_from_row. Rain counters take the highest reading rather than the mean, so that a
+// reset during the window doesn't leave it part way between the two. Gusts and UV are peaks, so
+// also take the highest. Fields with no readings in a window are NULL.
+const MEAN_COLUMNS: &str = r#"AVG(tempc), AVG(tempinc), AVG(humidity), AVG(humidityin),
+AVG(windspeedkph), MAX(windgustkph), AVG(wind_u(windspeedkph, winddir)), AVG(rainratemm),
+MAX(totalrainmm), MAX(uv), AVG(solarradiation), AVG(baromrelhpa), AVG(baromabshpa),
+MAX(dailyrainmm), MAX(eventrainmm), AVG(wind_v(windspeedkph, winddir))"#;
+
+pub(crate) struct SqliteStore {
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl SqliteStore {
+    pub(crate) fn open(path: &str) -> Result<Self, ApiError> {
+        let connection = Connection::open(path)?;
+        connection.pragma_update(None, "journal_mode", "WAL")?;
+        connection.execute(SCHEMA, [])?;
+        register_wind_functions(&connection)?;
+        Ok(Self {
+            connection: Arc::new(Mutex::new(connection)),
+        })
+    }
+
+    async fn with_connection<T, F>(&self, f: F) -> Result<T, ApiError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Connection) -> Result<T, rusqlite::Error> + Send + 'static,
+    {
+        let connection = self.connection.clone();
+        tokio::task::spawn_blocking(move || {
+            let connection = connection.lock().unwrap();
+            f(&connection)
+        })
+        .await
+        .map_err(|err| ApiError::Other(err.to_string()))?
+        .map_err(ApiError::Sqlite)
+    }
+}
+
+fn to_datetime(timestamp: i64) -> DateTime<FixedOffset> {
+    DateTime::from_timestamp(timestamp, 0_u32)
+        .unwrap()
+        .fixed_offset()
+}
+
+// The bundled SQLite is built without math functions, so the wind components are computed here
+fn register_wind_functions(connection: &Connection) -> Result<(), rusqlite::Error> {
+    for (name, component) in [("wind_u", 0), ("wind_v", 1)] {
+        connection.create_scalar_function(
+            name,
+            2,
+            FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+            move |context| {
+                let speed: Option<f64> = context.get(0)?;
+                let direction: Option<f64> = context.get(1)?;
+                Ok(speed.zip(direction).map(|(speed, direction)| {
+                    let (u, v) = components(speed, direction);
+                    if component == 0 { u } else { v }
+                }))
+            },
+        )?;
+    }
+    Ok(())
+}
+
+fn hour_record_from_row(row: &Row) -> Result<HourRecordFlux, rusqlite::Error> {
+    Ok(HourRecordFlux {
+        time: to_datetime(row.get(0)?),
+        tempc: row.get(1)?,
+        tempinc: row.get(2)?,
+        humidity: row.get(3)?,
+        humidityin: row.get(4)?,
+        windspeedkph: row.get(5)?,
+        windgustkph: row.get(6)?,
+        winddir: row.get(7)?,
+        windsteadiness: 1_f64,
+        rainratemm: row.get(8)?,
+        totalrainmm: row.get(9)?,
+        dailyrainmm: row.get(14)?,
+        eventrainmm: row.get(15)?,
+        uv: row.get(10)?,
+        solarradiation: row.get(11)?,
+        baromrelhpa: row.get(12)?,
+        baromabshpa: row.get(13)?,
+    })
+}
+
+fn window_record_from_row(row: &Row) -> Result<HourRecordFlux, rusqlite::Error> {
+    let (winddir, windsteadiness) = window_vector_mean(row.get(7)?, row.get(16)?, row.get(5)?);
+    Ok(HourRecordFlux {
+        winddir,
+        windsteadiness,
+        ..hour_record_from_row(row)?
+    })
+}
+
+fn day_summaries(
+    connection: &Connection,
+    start: i64,
+    end: i64,
+    elevation: Option<f64>,
+    rain_day: RainDayBoundary,
+) -> Result<Vec<DaySummary>, rusqlite::Error> {
+    let mut statement = connection.prepare(
+        r#"SELECT time, tempc, tempinc, humidity, humidityin, uv, solarradiation, totalrainmm,
+baromabshpa, baromrelhpa, dailyrainmm, eventrainmm, windgustkph
+FROM observations WHERE time >= ?1 AND time < ?2 ORDER BY time"#,
+    )?;
+    let mut rows = statement.query(params![start, end])?;
+
+    let mut days: Vec<DaySummary> = Vec::new();
+    let mut counters = Vec::new();
+    while let Some(row) = rows.next()? {
+        let time = to_datetime(row.get(0)?);
+        // Converted one at a time, so days either side of a daylight saving change both line up
+        let date = time.with_timezone(&Local).date_naive();
+        if days.last().is_none_or(|day| day.date != date) {
+            days.push(DaySummary::new(date));
+        }
+        let day = days.last_mut().unwrap();
+        day.tempc.add(row.get(1)?);
+        day.tempinc.add(row.get(2)?);
+        day.humidity.add(row.get(3)?);
+        day.humidityin.add(row.get(4)?);
+        day.uv.add(row.get(5)?);
+        day.solarradiation.add(row.get(6)?);
+        day.windgustkph.add(row.get(12)?);
+        day.baromslhpa.add(observation_sea_level_pressure(
+            row.get(8)?,
+            row.get(9)?,
+            elevation,
+            None,
+        ));
+
+        counters.push(RainCounters {
+            time,
+            totalrainmm: row.get(7)?,
+            dailyrainmm: row.get(10)?,
+            eventrainmm: row.get(11)?,
+        });
+    }
+
+    add_rain(&mut days, rain_by_day(counters, rain_day));
+    Ok(days)
+}
+
+#[async_trait]
+impl WeatherStore for SqliteStore {
+    async fn range(
+        &self,
+        start: DateTime<FixedOffset>,
+        end: DateTime<FixedOffset>,
+        resolution: Resolution,
+    ) -> Result<Vec<HourRecordFlux>, ApiError> {
+        let (start, end) = (start.timestamp(), end.timestamp());
+        let offset = resolution
+            .window_offset(local_utc_offset())
+            .map(|offset| offset.num_seconds());
+        self.with_connection(move |connection| {
+            let (sql, from_row): (String, fn(&Row) -> Result<HourRecordFlux, rusqlite::Error>) =
+                match resolution.seconds().zip(offset) {
+                    Some((window, offset)) => (
+                        format!(
+                            r#"SELECT MIN(((time - {offset}) / {window} + 1) * {window} + {offset}, ?2) AS window, {MEAN_COLUMNS}
+FROM observations WHERE time >= ?1 AND time < ?2 GROUP BY (time - {offset}) / {window} ORDER BY window"#
+                        ),
+                        window_record_from_row,
+                    ),
+                    None => (
+                        format!(
+                            r#"SELECT time, {RAW_COLUMNS}
+FROM observations WHERE time >= ?1 AND time < ?2 ORDER BY time"#
+                        ),
+                        hour_record_from_row,
+                    ),
+                };
+            let mut statement = connection.prepare(&sql)?;
+            statement.query_map(params![start, end], from_row)?.collect()
+        })
+        .await
+    }
+
+    async fn day_summaries(
+        &self,
+        start: DateTime<FixedOffset>,
+        end: DateTime<FixedOffset>,
+        elevation: Option<f64>,
+        rain_day: RainDayBoundary,
+    ) -> Result<Vec<DaySummary>, ApiError> {
+        let (start, end) = (start.timestamp(), end.timestamp());
+        self.with_connection(move |connection| {
+            day_summaries(connection, start, end, elevation, rain_day)
+        })
+        .await
+    }
+
+    async fn latest(&self) -> Result<Option<HourRecordFlux>, ApiError> {
+        let since = (Utc::now() - LATEST_WITHIN).timestamp();
+        self.with_connection(move |connection| {
+            let sql = format!(
+                r#"SELECT time, {RAW_COLUMNS}
+FROM observations WHERE time >= ?1 ORDER BY time DESC LIMIT 1"#
+            );
+            let mut statement = connection.prepare(&sql)?;
+            let mut rows = statement.query_map(params![since], hour_record_from_row)?;
+            rows.next().transpose()
+        })
+        .await
+    }
+
+    async fn observation_times(
+        &self,
+        start: DateTime<FixedOffset>,
+        end: DateTime<FixedOffset>,
+    ) -> Result<Vec<DateTime<FixedOffset>>, ApiError> {
+        let (start, end) = (start.timestamp(), end.timestamp());
+        self.with_connection(move |connection| {
+            let mut statement = connection
+                .prepare("SELECT time FROM observations WHERE time >= ?1 AND time < ?2")?;
+            statement
+                .query_map(params![start, end], |row| Ok(to_datetime(row.get(0)?)))?
+                .collect()
+        })
+        .await
+    }
+
+    async fn write(&self, observations: Vec<Observation>) -> Result<(), ApiError> {
+        self.with_connection(move |connection| {
+            let transaction = connection.unchecked_transaction()?;
+            {
+                let mut statement = transaction.prepare(
+                    r#"INSERT OR REPLACE INTO observations (time, model, tempc, tempinc, humidity, humidityin,
+windspeedkph, windgustkph, winddir, rainratemm, totalrainmm, dailyrainmm, eventrainmm, uv,
+solarradiation, baromrelhpa, baromabshpa)
+VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)"#,
+                )?;
+                for observation in observations {
+                    statement.execute(params![
+                        observation.time.timestamp(),
+                        observation.model,
+                        observation.tempc,
+                        observation.tempinc,
+                        observation.humidity,
+                        observation.humidityin,
+                        observation.windspeedkph,
+                        observation.windgustkph,
+                        observation.winddir,
+                        observation.rainratemm,
+                        observation.totalrainmm,
+                        observation.dailyrainmm,
+                        observation.eventrainmm,
+                        observation.uv,
+                        observation.solarradiation,
+                        observation.baromrelhpa,
+                        observation.baromabshpa,
+                    ])?;
+                }
+            }
+            transaction.commit()
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wind::vector_mean;
+
+    fn time(value: &str) -> DateTime<FixedOffset> {
+        DateTime::parse_from_rfc3339(value).unwrap()
+    }
+
+    #[tokio::test]
+    async fn missing_readings_are_read_as_none() {
+        let store = SqliteStore::open(":memory:").unwrap();
+        store
+            .write(vec![
+                Observation {
+                    time: time("2025-01-01T00:00:00Z"),
+                    tempc: Some(-2.5),
+                    windspeedkph: Some(12.0),
+                    winddir: Some(90.0),
+                    ..Default::default()
+                },
+                Observation {
+                    time: time("2025-01-01T00:05:00Z"),
+                    tempc: Some(-1.5),
+                    humidity: None,
+                    ..Default::default()
+                },
+            ])
+            .await
+            .unwrap();
+        let (start, end) = (time("2025-01-01T00:00:00Z"), time("2025-01-01T01:00:00Z"));
+
+        let raw = store.range(start, end, Resolution::Raw).await.unwrap();
+        assert_eq!(raw.len(), 2);
+        assert_eq!(raw[0].tempc, Some(-2.5));
+        assert_eq!(raw[0].humidity, None);
+        assert_eq!(raw[0].dailyrainmm, None);
+        assert_eq!(raw[1].windspeedkph, None);
+        assert_eq!(raw[1].winddir, None);
+
+        let windows = store.range(start, end, Resolution::Hour).await.unwrap();
+        assert_eq!(windows.len(), 1);
+        assert_eq!(windows[0].tempc, Some(-2.0));
+        assert_eq!(windows[0].humidity, None);
+        assert_eq!(windows[0].baromrelhpa, None);
+        // The mean wind only comes from the observation that reported it
+        assert_eq!(windows[0].windspeedkph, Some(12.0));
+        assert!((windows[0].winddir.unwrap() - 90.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn wind_components_average_across_north() {
+        let connection = Connection::open_in_memory().unwrap();
+        register_wind_functions(&connection).unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE wind (speed REAL, direction REAL);
+                 INSERT INTO wind VALUES (10, 350), (10, 10), (0, 180), (NULL, 90), (5, NULL);",
+            )
+            .unwrap();
+        let (u, v, speed): (f64, f64, f64) = connection
+            .query_row(
+                "SELECT AVG(wind_u(speed, direction)), AVG(wind_v(speed, direction)), AVG(speed) \
+                 FROM wind WHERE direction IS NOT NULL",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        let (direction, steadiness) = vector_mean(u, v, speed);
+        assert!(direction.min(360.0 - direction) < 1e-9, "{direction}");
+        // Rows without a speed are left out of both means, and the calm row has no direction
+        assert!((steadiness - 10_f64.to_radians().cos()).abs() < 1e-9);
+    }
+}
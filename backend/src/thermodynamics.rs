@@ -0,0 +1,169 @@
+use crate::units::{celsius_to_fahrenheit, fahrenheit_to_celsius};
+
+// Magnus formula constants for water over liquid, matching feels_like_temperature
+const MAGNUS_A: f64 = 17.27;
+const MAGNUS_B: f64 = 237.7;
+
+// Height in metres that cumulus cloud base rises for each degree of dew point depression
+const CLOUD_BASE_PER_DEGREE: f64 = 125_f64;
+
+pub(crate) fn dew_point(tempc: f64, humidity: f64) -> f64 {
+    let alpha = (MAGNUS_A * tempc) / (MAGNUS_B + tempc) + (humidity.max(1_f64) / 100_f64).ln();
+    (MAGNUS_B * alpha) / (MAGNUS_A - alpha)
+}
+
+/// NWS heat index, using the Rothfusz regression where Steadman's simpler formula gives 80°F or
+/// more
+pub(crate) fn heat_index(tempc: f64, humidity: f64) -> f64 {
+    let t = celsius_to_fahrenheit(tempc);
+    let rh = humidity;
+
+    let simple = 0.5 * (t + 61_f64 + (t - 68_f64) * 1.2 + rh * 0.094);
+    if (simple + t) / 2_f64 < 80_f64 {
+        return fahrenheit_to_celsius(simple);
+    }
+
+    let mut index = -42.379 + 2.04901523 * t + 10.14333127 * rh
+        - 0.22475541 * t * rh
+        - 0.00683783 * t * t
+        - 0.05481717 * rh * rh
+        + 0.00122874 * t * t * rh
+        + 0.00085282 * t * rh * rh
+        - 0.00000199 * t * t * rh * rh;
+    if rh < 13_f64 && (80_f64..=112_f64).contains(&t) {
+        index -= ((13_f64 - rh) / 4_f64) * ((17_f64 - (t - 95_f64).abs()) / 17_f64).sqrt();
+    } else if rh > 85_f64 && (80_f64..=87_f64).contains(&t) {
+        index += ((rh - 85_f64) / 10_f64) * ((87_f64 - t) / 5_f64);
+    }
+    fahrenheit_to_celsius(index)
+}
+
+/// North American wind chill index, which is only defined at or below 10°C with wind above
+/// 4.8 km/h, otherwise the air temperature
+pub(crate) fn wind_chill(tempc: f64, windspeedkph: f64) -> f64 {
+    if tempc > 10_f64 || windspeedkph <= 4.8 {
+        return tempc;
+    }
+    let wind = windspeedkph.powf(0.16);
+    13.12 + 0.6215 * tempc - 11.37 * wind + 0.3965 * tempc * wind
+}
+
+/// Environment Canada humidex
+pub(crate) fn humidex(tempc: f64, dewpoint: f64) -> f64 {
+    let vapour_pressure = 6.11 * (5417.7530 * (1_f64 / 273.16 - 1_f64 / (273.15 + dewpoint))).exp();
+    tempc + 0.5555 * (vapour_pressure - 10_f64)
+}
+
+/// Wet-bulb temperature from Stull's (2011) empirical fit, accurate to within 1°C for relative
+/// humidities of 5-99% and temperatures of -20-50°C at sea-level pressure
+pub(crate) fn wet_bulb(tempc: f64, humidity: f64) -> f64 {
+    tempc * (0.151977 * (humidity + 8.313659).sqrt()).atan() + (tempc + humidity).atan()
+        - (humidity - 1.676331).atan()
+        + 0.00391838 * humidity.powf(1.5) * (0.023101 * humidity).atan()
+        - 4.686035
+}
+
+/// Water vapour density in g/m³
+pub(crate) fn absolute_humidity(tempc: f64, humidity: f64) -> f64 {
+    let saturation_pressure = 6.112 * ((17.67 * tempc) / (tempc + 243.5)).exp();
+    saturation_pressure * humidity * 2.1674 / (273.15 + tempc)
+}
+
+/// Estimated height of cumulus cloud base above the station in metres
+pub(crate) fn cloud_base(tempc: f64, dewpoint: f64) -> f64 {
+    ((tempc - dewpoint) * CLOUD_BASE_PER_DEGREE).max(0_f64)
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Thermodynamics {
+    pub(crate) dewpoint: f64,
+    pub(crate) heatindex: f64,
+    pub(crate) windchill: f64,
+    pub(crate) humidex: f64,
+    pub(crate) wetbulb: f64,
+    pub(crate) absolutehumidity: f64,
+    pub(crate) cloudbase: f64,
+}
+
+impl Thermodynamics {
+    /// From an observation's readings, if it has both temperature and humidity. Missing wind is
+    /// taken as calm.
+    pub(crate) fn from_readings(
+        tempc: Option<f64>,
+        humidity: Option<f64>,
+        windspeedkph: Option<f64>,
+    ) -> Option<Self> {
+        let (tempc, humidity) = tempc.zip(humidity)?;
+        Some(Self::new(tempc, humidity, windspeedkph.unwrap_or(0_f64)))
+    }
+
+    pub(crate) fn new(tempc: f64, humidity: f64, windspeedkph: f64) -> Self {
+        let dewpoint = dew_point(tempc, humidity);
+        Self {
+            dewpoint,
+            heatindex: heat_index(tempc, humidity),
+            windchill: wind_chill(tempc, windspeedkph),
+            humidex: humidex(tempc, dewpoint),
+            wetbulb: wet_bulb(tempc, humidity),
+            absolutehumidity: absolute_humidity(tempc, humidity),
+            cloudbase: cloud_base(tempc, dewpoint),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "{actual} != {expected}"
+        );
+    }
+
+    #[test]
+    fn dew_point_known_values() {
+        assert_close(dew_point(20_f64, 50_f64), 9.3, 0.1);
+        assert_close(dew_point(30_f64, 100_f64), 30_f64, 1e-9);
+    }
+
+    #[test]
+    fn heat_index_known_values() {
+        // NWS table: 90°F at 70% is 106°F
+        assert_close(heat_index(32.22, 70_f64), 41.1, 0.2);
+        // Below 80°F Steadman's formula is used: 68°F at 50% gives 66.85°F
+        assert_close(heat_index(20_f64, 50_f64), 19.36, 0.01);
+    }
+
+    #[test]
+    fn wind_chill_known_values() {
+        // Environment Canada table: -10°C with 20 km/h wind is -17.9°C
+        assert_close(wind_chill(-10_f64, 20_f64), -17.9, 0.1);
+        assert_close(wind_chill(-20_f64, 50_f64), -35.4, 0.1);
+        assert_eq!(wind_chill(15_f64, 30_f64), 15_f64);
+        assert_eq!(wind_chill(0_f64, 4_f64), 0_f64);
+    }
+
+    #[test]
+    fn humidex_known_values() {
+        // Environment Canada: 30°C with a 15°C dew point is a humidex of 34
+        assert_close(humidex(30_f64, 15_f64), 34_f64, 0.1);
+        assert_close(humidex(25_f64, 20_f64), 33_f64, 0.5);
+    }
+
+    #[test]
+    fn wet_bulb_known_values() {
+        // Stull (2011): 20°C at 50% is 13.7°C
+        assert_close(wet_bulb(20_f64, 50_f64), 13.7, 0.1);
+        assert_close(wet_bulb(30_f64, 99_f64), 30_f64, 0.5);
+    }
+
+    #[test]
+    fn humidity_and_cloud_base() {
+        // Saturated air at 20°C holds 17.3 g/m³
+        assert_close(absolute_humidity(20_f64, 100_f64), 17.3, 0.1);
+        assert_eq!(cloud_base(20_f64, 10_f64), 1250_f64);
+        assert_eq!(cloud_base(10_f64, 10.5), 0_f64);
+    }
+}
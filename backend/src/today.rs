@@ -0,0 +1,264 @@
+use crate::ApiError::Unavailable;
+use crate::forecast::zambretti;
+use crate::pressure::{PressureTendency, observation_sea_level_pressure};
+use crate::rain::{RainAccumulator, RainDayBoundary, local_time};
+use crate::records::{all_day_summaries, new_records, records};
+use crate::sky::{classify, clear_sky_radiation};
+use crate::stats::DaySummary;
+use crate::thermodynamics::Thermodynamics;
+use crate::types::{HourRecordFlux, Records, TodayDataWithDerivedTypes};
+use crate::wind::{cardinal, components, vector_mean};
+use crate::{ApiError, ServerState, feels_like_temperature, pressure_change};
+use axum::Json;
+use axum::extract::State;
+use chrono::{DateTime, Datelike, Duration, FixedOffset, Local, NaiveDate};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use sunrise::{SolarDay, SolarEvent};
+use tokio::sync::watch;
+
+// Observations are kept for this long, to find the pressure three hours earlier
+const RECENT_MINUTES: i64 = 3 * 60 + 15;
+
+// The station is reported as stale when nothing has been received for this long
+const STALE_MINUTES: i64 = 10;
+
+/// Today's conditions, or None until the first observation has been read
+pub(crate) type TodaySender = watch::Sender<Option<TodayDataWithDerivedTypes>>;
+
+/// Rain since the start of the current rain day for one boundary
+struct RainTotal {
+    boundary: RainDayBoundary,
+    start: Option<DateTime<FixedOffset>>,
+    total: f64,
+}
+
+impl RainTotal {
+    fn new(boundary: RainDayBoundary) -> Self {
+        Self {
+            boundary,
+            start: None,
+            total: 0_f64,
+        }
+    }
+
+    fn add(&mut self, time: DateTime<FixedOffset>, rain: f64) {
+        let start = self.boundary.current_start(&time);
+        if self.start != Some(start) {
+            self.start = Some(start);
+            self.total = 0_f64;
+        }
+        if time > start {
+            self.total += rain;
+        }
+    }
+}
+
+/// Keeps today's extremes, rain and latest observation up to date as observations arrive, so that
+/// requests don't have to query the store
+pub(crate) struct TodayTracker {
+    summary: DaySummary,
+    records: Option<Records>,
+    recent: VecDeque<HourRecordFlux>,
+    rain: RainAccumulator,
+    rain_day: RainTotal,
+    since_9am: RainTotal,
+}
+
+impl TodayTracker {
+    pub(crate) fn new(rain_day: RainDayBoundary) -> Self {
+        Self {
+            summary: DaySummary::new(NaiveDate::MIN),
+            records: None,
+            recent: VecDeque::new(),
+            rain: RainAccumulator::default(),
+            rain_day: RainTotal::new(rain_day),
+            since_9am: RainTotal::new(RainDayBoundary::NineAm),
+        }
+    }
+
+    pub(crate) fn latest_time(&self) -> Option<DateTime<FixedOffset>> {
+        self.recent.back().map(|datum| datum.time)
+    }
+
+    /// Where to start reading observations on startup, so that everything tracked for the day
+    /// containing time is covered, along with an earlier observation to measure rain from
+    pub(crate) fn backfill_start(&self, time: DateTime<FixedOffset>) -> DateTime<FixedOffset> {
+        let midnight = local_time(time.with_timezone(&Local).date_naive(), 0);
+        [
+            midnight,
+            self.rain_day.boundary.current_start(&time),
+            self.since_9am.boundary.current_start(&time),
+            time - Duration::minutes(RECENT_MINUTES),
+        ]
+        .into_iter()
+        .min()
+        .unwrap()
+            - Duration::minutes(15)
+    }
+
+    pub(crate) fn add(&mut self, state: &ServerState, datum: HourRecordFlux) {
+        let date = datum.time.with_timezone(&Local).date_naive();
+        if date != self.summary.date {
+            self.summary = DaySummary::new(date);
+            self.records = None;
+        }
+
+        let rain = self.rain.add((&datum).into()).unwrap_or(0_f64);
+        self.rain_day.add(datum.time, rain);
+        self.since_9am.add(datum.time, rain);
+
+        let summary = &mut self.summary;
+        summary.tempc.add(datum.tempc);
+        summary.tempinc.add(datum.tempinc);
+        summary.humidity.add(datum.humidity);
+        summary.humidityin.add(datum.humidityin);
+        summary.uv.add(datum.uv);
+        summary.solarradiation.add(datum.solarradiation);
+        summary.windgustkph.add(datum.windgustkph);
+        summary.baromslhpa.add(observation_sea_level_pressure(
+            datum.baromabshpa,
+            datum.baromrelhpa,
+            state.elevation,
+            datum.tempc,
+        ));
+        summary.rainmm = self.rain_day.total;
+
+        let oldest = datum.time - Duration::minutes(RECENT_MINUTES);
+        self.recent.push_back(datum);
+        while self.recent.front().is_some_and(|datum| datum.time < oldest) {
+            self.recent.pop_front();
+        }
+    }
+
+    /// Records are compared against those set before today, so are read once a day
+    pub(crate) async fn refresh_records(&mut self, state: &ServerState) -> Result<(), ApiError> {
+        if self.records.is_some() || self.recent.is_empty() {
+            return Ok(());
+        }
+        let mut days = all_day_summaries(state).await?;
+        days.retain(|day| day.date < self.summary.date);
+        self.records = Some(records(&days));
+        Ok(())
+    }
+
+    /// Steadiness of the wind over the last hour, from the observations with a wind reading
+    fn windsteadiness(&self, time: DateTime<FixedOffset>) -> f64 {
+        let hour: Vec<(f64, f64)> = self
+            .recent
+            .iter()
+            .filter(|datum| datum.time > time - Duration::hours(1))
+            .filter_map(|datum| datum.windspeedkph.zip(datum.winddir))
+            .collect();
+        if hour.is_empty() {
+            return 0_f64;
+        }
+        let count = hour.len() as f64;
+        let (u, v, speed) = hour.iter().fold(
+            (0_f64, 0_f64, 0_f64),
+            |(u, v, speed), (windspeedkph, winddir)| {
+                let (du, dv) = components(*windspeedkph, *winddir);
+                (u + du, v + dv, speed + windspeedkph)
+            },
+        );
+        vector_mean(u / count, v / count, speed / count).1
+    }
+
+    pub(crate) fn snapshot(&self, state: &ServerState) -> Option<TodayDataWithDerivedTypes> {
+        let last = self.recent.back()?;
+        let mut result = TodayDataWithDerivedTypes {
+            time: last.time,
+            tempc: last.tempc,
+            tempinc: last.tempinc,
+            humidity: last.humidity,
+            humidityin: last.humidityin,
+            windspeedkph: last.windspeedkph,
+            windgustkph: last.windgustkph,
+            winddir: last.winddir,
+            windsteadiness: self.windsteadiness(last.time),
+            windcardinal: last.winddir.map(|winddir| cardinal(winddir).to_string()),
+            rainratemm: last.rainratemm,
+            totalrainmm: self.rain_day.total,
+            rainsince9am: self.since_9am.total,
+            uv: last.uv,
+            mintemp: self.summary.tempc.min_value(),
+            maxtemp: self.summary.tempc.max_value(),
+            mintempin: self.summary.tempinc.min_value(),
+            maxtempin: self.summary.tempinc.max_value(),
+            maxuv: self.summary.uv.max_value(),
+            solarradiation: last.solarradiation,
+            baromrelhpa: last.baromrelhpa,
+            baromabshpa: last.baromabshpa,
+            ..Default::default()
+        };
+        let solar_day = SolarDay::new(state.coordinates, self.summary.date);
+        result.sunrise = solar_day
+            .event_time(SolarEvent::Sunrise)
+            .with_timezone(&Local)
+            .to_rfc3339();
+        result.sunset = solar_day
+            .event_time(SolarEvent::Sunset)
+            .with_timezone(&Local)
+            .to_rfc3339();
+        let (baromslhpa, change) = pressure_change(state, last, &self.recent);
+        result.baromslhpa = baromslhpa;
+        result.pressurechange3h = change;
+        result.pressuretendency = change.map(PressureTendency::from_change);
+        result.forecast = baromslhpa
+            .zip(result.pressuretendency)
+            .map(|(baromslhpa, tendency)| {
+                zambretti(
+                    baromslhpa,
+                    tendency,
+                    last.windspeedkph
+                        .filter(|speed| *speed > 0_f64)
+                        .and(last.winddir),
+                    state.coordinates.lat(),
+                    self.summary.date.month(),
+                )
+            });
+        let conditions = classify(
+            last.solarradiation,
+            clear_sky_radiation(last.time, &state.coordinates),
+            last.rainratemm.is_some_and(|rate| rate > 0_f64),
+        );
+        result.clearskyradiation = conditions.clearskyradiation;
+        result.clearnessindex = conditions.clearnessindex;
+        result.cloudcover = conditions.cloudcover;
+        result.sky = conditions.sky;
+        result.feelslike =
+            feels_like_temperature(result.tempc, result.humidity, result.windspeedkph);
+        result.feelslikein = feels_like_temperature(result.tempinc, result.humidityin, None);
+        let thermodynamics =
+            Thermodynamics::from_readings(result.tempc, result.humidity, result.windspeedkph);
+        result.dewpoint = thermodynamics.map(|t| t.dewpoint);
+        result.heatindex = thermodynamics.map(|t| t.heatindex);
+        result.windchill = thermodynamics.map(|t| t.windchill);
+        result.humidex = thermodynamics.map(|t| t.humidex);
+        result.wetbulb = thermodynamics.map(|t| t.wetbulb);
+        result.absolutehumidity = thermodynamics.map(|t| t.absolutehumidity);
+        result.cloudbase = thermodynamics.map(|t| t.cloudbase);
+        if let Some(records) = &self.records {
+            result.newrecords = new_records(records, &self.summary, self.rain_day.total);
+        }
+        Some(result)
+    }
+}
+
+pub(crate) async fn today(
+    State(state): State<Arc<ServerState>>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let now = Local::now();
+    let Some(mut result) = state.today.borrow().clone() else {
+        return Err(Unavailable(
+            "No observations have been read yet".to_string(),
+        ));
+    };
+    // Without anything received today, the last known conditions are returned as stale
+    let received_today = result.time.with_timezone(&Local).date_naive() == now.date_naive();
+    result.observationage = (now.fixed_offset() - result.time).num_seconds().max(0);
+    result.stale =
+        !received_today || result.observationage > Duration::minutes(STALE_MINUTES).num_seconds();
+
+    Ok(Json(serde_json::to_value(result).unwrap()))
+}
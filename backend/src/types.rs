@@ -1,64 +1,118 @@
-use chrono::{DateTime, FixedOffset};
+use crate::forecast::Forecast;
+use crate::pressure::PressureTendency;
+use crate::sky::Sky;
+use crate::thermodynamics::Thermodynamics;
+use crate::wind::cardinal;
+use chrono::{DateTime, FixedOffset, NaiveDate};
 use influxdb2::FromDataPoint;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize, Clone, FromDataPoint)]
+/// An observation, or the aggregate of a window of them. Readings the station didn't report, or
+/// that were rejected as implausible, are None.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub(crate) struct HourRecordFlux {
     pub(crate) time: DateTime<FixedOffset>,
-    pub(crate) tempc: f64,
-    pub(crate) tempinc: f64,
-    pub(crate) humidity: f64,
-    pub(crate) humidityin: f64,
-    pub(crate) windspeedkph: f64,
-    pub(crate) windgustkph: f64,
-    pub(crate) winddir: f64,
-    pub(crate) rainratemm: f64,
-    pub(crate) totalrainmm: f64,
-    pub(crate) uv: f64,
-    pub(crate) solarradiation: f64,
+    pub(crate) tempc: Option<f64>,
+    pub(crate) tempinc: Option<f64>,
+    pub(crate) humidity: Option<f64>,
+    pub(crate) humidityin: Option<f64>,
+    pub(crate) windspeedkph: Option<f64>,
+    pub(crate) windgustkph: Option<f64>,
+    pub(crate) winddir: Option<f64>,
+    pub(crate) windsteadiness: f64,
+    pub(crate) rainratemm: Option<f64>,
+    pub(crate) totalrainmm: Option<f64>,
+    /// The station's own daily and event rain counters
+    pub(crate) dailyrainmm: Option<f64>,
+    pub(crate) eventrainmm: Option<f64>,
+    pub(crate) uv: Option<f64>,
+    pub(crate) solarradiation: Option<f64>,
+    pub(crate) baromrelhpa: Option<f64>,
+    pub(crate) baromabshpa: Option<f64>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub(crate) struct HourRecordWithDerivedTypes {
     pub(crate) time: DateTime<FixedOffset>,
-    pub(crate) tempc: f64,
-    pub(crate) tempinc: f64,
-    pub(crate) humidity: f64,
-    pub(crate) humidityin: f64,
-    pub(crate) windspeedkph: f64,
-    pub(crate) windgustkph: f64,
-    pub(crate) winddir: f64,
-    pub(crate) rainratemm: f64,
+    pub(crate) tempc: Option<f64>,
+    pub(crate) tempinc: Option<f64>,
+    pub(crate) humidity: Option<f64>,
+    pub(crate) humidityin: Option<f64>,
+    pub(crate) windspeedkph: Option<f64>,
+    pub(crate) windgustkph: Option<f64>,
+    pub(crate) winddir: Option<f64>,
+    pub(crate) windsteadiness: f64,
+    pub(crate) windcardinal: Option<String>,
+    pub(crate) rainratemm: Option<f64>,
     pub(crate) totalrainmm: f64,
-    pub(crate) uv: f64,
-    pub(crate) solarradiation: f64,
-    pub(crate) feelslike: f64,
-    pub(crate) feelslikein: f64,
+    pub(crate) uv: Option<f64>,
+    pub(crate) solarradiation: Option<f64>,
+    pub(crate) baromrelhpa: Option<f64>,
+    pub(crate) baromabshpa: Option<f64>,
+    pub(crate) baromslhpa: Option<f64>,
+    pub(crate) clearskyradiation: f64,
+    pub(crate) clearnessindex: Option<f64>,
+    pub(crate) cloudcover: Option<f64>,
+    pub(crate) sky: Option<Sky>,
+    pub(crate) feelslike: Option<f64>,
+    pub(crate) feelslikein: Option<f64>,
+    pub(crate) dewpoint: Option<f64>,
+    pub(crate) heatindex: Option<f64>,
+    pub(crate) windchill: Option<f64>,
+    pub(crate) humidex: Option<f64>,
+    pub(crate) wetbulb: Option<f64>,
+    pub(crate) absolutehumidity: Option<f64>,
+    pub(crate) cloudbase: Option<f64>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub(crate) struct TodayDataWithDerivedTypes {
     pub(crate) time: DateTime<FixedOffset>,
-    pub(crate) tempc: f64,
-    pub(crate) tempinc: f64,
-    pub(crate) humidity: f64,
-    pub(crate) humidityin: f64,
-    pub(crate) windspeedkph: f64,
-    pub(crate) windgustkph: f64,
-    pub(crate) winddir: f64,
-    pub(crate) rainratemm: f64,
+    pub(crate) tempc: Option<f64>,
+    pub(crate) tempinc: Option<f64>,
+    pub(crate) humidity: Option<f64>,
+    pub(crate) humidityin: Option<f64>,
+    pub(crate) windspeedkph: Option<f64>,
+    pub(crate) windgustkph: Option<f64>,
+    pub(crate) winddir: Option<f64>,
+    pub(crate) windsteadiness: f64,
+    pub(crate) windcardinal: Option<String>,
+    pub(crate) rainratemm: Option<f64>,
     pub(crate) totalrainmm: f64,
-    pub(crate) uv: f64,
-    pub(crate) mintemp: f64,
-    pub(crate) maxtemp: f64,
-    pub(crate) mintempin: f64,
-    pub(crate) maxtempin: f64,
+    pub(crate) rainsince9am: f64,
+    pub(crate) uv: Option<f64>,
+    pub(crate) mintemp: Option<f64>,
+    pub(crate) maxtemp: Option<f64>,
+    pub(crate) mintempin: Option<f64>,
+    pub(crate) maxtempin: Option<f64>,
     pub(crate) sunrise: String,
     pub(crate) sunset: String,
-    pub(crate) maxuv: f64,
-    pub(crate) solarradiation: f64,
-    pub(crate) feelslike: f64,
-    pub(crate) feelslikein: f64,
+    pub(crate) maxuv: Option<f64>,
+    pub(crate) solarradiation: Option<f64>,
+    pub(crate) baromrelhpa: Option<f64>,
+    pub(crate) baromabshpa: Option<f64>,
+    pub(crate) baromslhpa: Option<f64>,
+    pub(crate) pressurechange3h: Option<f64>,
+    pub(crate) pressuretendency: Option<PressureTendency>,
+    pub(crate) forecast: Option<Forecast>,
+    pub(crate) newrecords: Vec<NewRecord>,
+    pub(crate) clearskyradiation: f64,
+    pub(crate) clearnessindex: Option<f64>,
+    pub(crate) cloudcover: Option<f64>,
+    pub(crate) sky: Option<Sky>,
+    pub(crate) feelslike: Option<f64>,
+    pub(crate) feelslikein: Option<f64>,
+    pub(crate) dewpoint: Option<f64>,
+    pub(crate) heatindex: Option<f64>,
+    pub(crate) windchill: Option<f64>,
+    pub(crate) humidex: Option<f64>,
+    pub(crate) wetbulb: Option<f64>,
+    pub(crate) absolutehumidity: Option<f64>,
+    pub(crate) cloudbase: Option<f64>,
+    /// Seconds since the latest observation
+    pub(crate) observationage: i64,
+    /// Whether the station has stopped reporting
+    pub(crate) stale: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, FromDataPoint)]
@@ -91,27 +145,101 @@ pub(crate) struct MonthRecordFlux {
     pub(crate) tempinc_mean_min: f64,
     pub(crate) totalrainmm: f64,
     pub(crate) raindayscount: i64,
+    /// Days with at least one observation
+    pub(crate) dayscount: i64,
     pub(crate) uv_absolute: f64,
     pub(crate) uv_mean: f64,
+    pub(crate) baromslhpa: f64,
+    pub(crate) baromslhpa_absolute_max: f64,
+    pub(crate) baromslhpa_absolute_min: f64,
+    pub(crate) baromslhpa_mean_max: f64,
+    pub(crate) baromslhpa_mean_min: f64,
 }
 
-impl Default for HourRecordFlux {
-    fn default() -> Self {
-        Self {
-            time: chrono::prelude::DateTime::from_timestamp(0_i64, 0_u32)
-                .unwrap()
-                .with_timezone(&FixedOffset::east_opt(0).unwrap()),
-            tempc: 0_f64,
-            tempinc: 0_f64,
-            humidity: 0_f64,
-            humidityin: 0_f64,
-            windspeedkph: 0_f64,
-            windgustkph: 0_f64,
-            winddir: 0_f64,
-            rainratemm: 0_f64,
-            totalrainmm: 0_f64,
-            uv: 0_f64,
-            solarradiation: f64::MIN,
+/// Monthly statistics with values derived from the monthly means. Heat index and humidex use the
+/// mean daily maximum temperature and mean daily minimum humidity, as a typical afternoon.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct MonthRecordWithDerivedTypes {
+    #[serde(flatten)]
+    pub(crate) record: MonthRecordFlux,
+    pub(crate) dewpoint: f64,
+    pub(crate) wetbulb: f64,
+    pub(crate) absolutehumidity: f64,
+    pub(crate) cloudbase: f64,
+    pub(crate) heatindex_mean_max: f64,
+    pub(crate) humidex_mean_max: f64,
+    /// Difference from the station's normal for the calendar month, if it has any complete years
+    pub(crate) anomalies: Option<MonthAnomalies>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub(crate) struct Normals {
+    pub(crate) tempc: f64,
+    pub(crate) tempc_mean_max: f64,
+    pub(crate) tempc_mean_min: f64,
+    pub(crate) tempc_absolute_max: f64,
+    pub(crate) tempc_absolute_min: f64,
+    pub(crate) humidity: f64,
+    pub(crate) solarradiation: f64,
+    pub(crate) totalrainmm: f64,
+    pub(crate) raindayscount: f64,
+}
+
+/// Long-term averages for a calendar month, from every month recorded on all of its days, other
+/// than the first month of records
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct MonthNormal {
+    pub(crate) month: u32,
+    pub(crate) years: u32,
+    #[serde(flatten)]
+    pub(crate) normals: Normals,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct MonthAnomalies {
+    pub(crate) tempc: f64,
+    pub(crate) tempc_mean_max: f64,
+    pub(crate) tempc_mean_min: f64,
+    pub(crate) totalrainmm: f64,
+    /// Rain as a percentage of normal, if normal is more than nothing
+    pub(crate) totalrainmm_percent: Option<f64>,
+    pub(crate) raindayscount: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct Climate {
+    pub(crate) first: Option<DateTime<FixedOffset>>,
+    pub(crate) last: Option<DateTime<FixedOffset>>,
+    pub(crate) months: Vec<MonthNormal>,
+    /// Normals for the whole year, once every calendar month has been recorded
+    pub(crate) annual: Option<Normals>,
+}
+
+/// Statistics for a calendar year, with the same fields as a month plus a few year-level counts
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct YearRecord {
+    #[serde(flatten)]
+    pub(crate) record: MonthRecordFlux,
+    /// Days with a maximum temperature above 30°C
+    pub(crate) hotdayscount: i64,
+    /// Days with a minimum temperature below 0°C
+    pub(crate) frostdayscount: i64,
+    pub(crate) wettestmonth: Option<WeatherRecord>,
+}
+
+impl From<MonthRecordFlux> for MonthRecordWithDerivedTypes {
+    fn from(record: MonthRecordFlux) -> MonthRecordWithDerivedTypes {
+        let mean = Thermodynamics::new(record.tempc, record.humidity, 0_f64);
+        let afternoon = Thermodynamics::new(record.tempc_mean_max, record.humidity_mean_min, 0_f64);
+        MonthRecordWithDerivedTypes {
+            dewpoint: mean.dewpoint,
+            wetbulb: mean.wetbulb,
+            absolutehumidity: mean.absolutehumidity,
+            cloudbase: mean.cloudbase,
+            heatindex_mean_max: afternoon.heatindex,
+            humidex_mean_max: afternoon.humidex,
+            anomalies: None,
+            record,
         }
     }
 }
@@ -122,39 +250,61 @@ impl Default for HourRecordWithDerivedTypes {
             time: chrono::prelude::DateTime::from_timestamp(0_i64, 0_u32)
                 .unwrap()
                 .with_timezone(&FixedOffset::east_opt(0).unwrap()),
-            tempc: 0_f64,
-            tempinc: 0_f64,
-            humidity: 0_f64,
-            humidityin: 0_f64,
-            windspeedkph: 0_f64,
-            windgustkph: 0_f64,
-            winddir: 0_f64,
-            rainratemm: 0_f64,
+            tempc: None,
+            tempinc: None,
+            humidity: None,
+            humidityin: None,
+            windspeedkph: None,
+            windgustkph: None,
+            winddir: None,
+            windsteadiness: 0_f64,
+            windcardinal: None,
+            rainratemm: None,
             totalrainmm: 0_f64,
-            uv: 0_f64,
-            solarradiation: f64::MIN,
-            feelslike: f64::MIN,
-            feelslikein: f64::MIN,
+            uv: None,
+            solarradiation: None,
+            baromrelhpa: None,
+            baromabshpa: None,
+            baromslhpa: None,
+            clearskyradiation: 0_f64,
+            clearnessindex: None,
+            cloudcover: None,
+            sky: None,
+            feelslike: None,
+            feelslikein: None,
+            dewpoint: None,
+            heatindex: None,
+            windchill: None,
+            humidex: None,
+            wetbulb: None,
+            absolutehumidity: None,
+            cloudbase: None,
         }
     }
 }
 
 impl From<HourRecordFlux> for HourRecordWithDerivedTypes {
     fn from(flux_record: HourRecordFlux) -> HourRecordWithDerivedTypes {
-        let mut new_record = HourRecordWithDerivedTypes::default();
-        new_record.time = flux_record.time;
-        new_record.tempc = flux_record.tempc;
-        new_record.tempinc = flux_record.tempinc;
-        new_record.humidity = flux_record.humidity;
-        new_record.humidityin = flux_record.humidityin;
-        new_record.windspeedkph = flux_record.windspeedkph;
-        new_record.windgustkph = flux_record.windgustkph;
-        new_record.winddir = flux_record.winddir;
-        new_record.rainratemm = flux_record.rainratemm;
-        new_record.totalrainmm = flux_record.totalrainmm;
-        new_record.uv = flux_record.uv;
-        new_record.solarradiation = flux_record.solarradiation;
-        new_record
+        HourRecordWithDerivedTypes {
+            time: flux_record.time,
+            tempc: flux_record.tempc,
+            tempinc: flux_record.tempinc,
+            humidity: flux_record.humidity,
+            humidityin: flux_record.humidityin,
+            windspeedkph: flux_record.windspeedkph,
+            windgustkph: flux_record.windgustkph,
+            winddir: flux_record.winddir,
+            windsteadiness: flux_record.windsteadiness,
+            windcardinal: flux_record
+                .winddir
+                .map(|winddir| cardinal(winddir).to_string()),
+            rainratemm: flux_record.rainratemm,
+            uv: flux_record.uv,
+            solarradiation: flux_record.solarradiation,
+            baromrelhpa: flux_record.baromrelhpa,
+            baromabshpa: flux_record.baromabshpa,
+            ..Default::default()
+        }
     }
 }
 
@@ -164,26 +314,49 @@ impl Default for TodayDataWithDerivedTypes {
             time: chrono::prelude::DateTime::from_timestamp(0_i64, 0_u32)
                 .unwrap()
                 .with_timezone(&FixedOffset::east_opt(0).unwrap()),
-            tempc: 0_f64,
-            tempinc: 0_f64,
-            humidity: 0_f64,
-            humidityin: 0_f64,
-            windspeedkph: 0_f64,
-            windgustkph: 0_f64,
-            winddir: 0_f64,
-            rainratemm: 0_f64,
+            tempc: None,
+            tempinc: None,
+            humidity: None,
+            humidityin: None,
+            windspeedkph: None,
+            windgustkph: None,
+            winddir: None,
+            windsteadiness: 0_f64,
+            windcardinal: None,
+            rainratemm: None,
             totalrainmm: 0_f64,
-            uv: 0_f64,
-            mintemp: f64::MAX,
-            maxtemp: f64::MIN,
-            mintempin: f64::MAX,
-            maxtempin: f64::MIN,
+            rainsince9am: 0_f64,
+            uv: None,
+            mintemp: None,
+            maxtemp: None,
+            mintempin: None,
+            maxtempin: None,
             sunrise: "".to_string(),
             sunset: "".to_string(),
-            maxuv: f64::MIN,
-            solarradiation: f64::MIN,
-            feelslike: f64::MIN,
-            feelslikein: f64::MIN,
+            maxuv: None,
+            solarradiation: None,
+            baromrelhpa: None,
+            baromabshpa: None,
+            baromslhpa: None,
+            pressurechange3h: None,
+            pressuretendency: None,
+            forecast: None,
+            newrecords: Vec::new(),
+            clearskyradiation: 0_f64,
+            clearnessindex: None,
+            cloudcover: None,
+            sky: None,
+            feelslike: None,
+            feelslikein: None,
+            dewpoint: None,
+            heatindex: None,
+            windchill: None,
+            humidex: None,
+            wetbulb: None,
+            absolutehumidity: None,
+            cloudbase: None,
+            observationage: 0_i64,
+            stale: false,
         }
     }
 }
@@ -221,8 +394,208 @@ impl Default for MonthRecordFlux {
             tempinc_mean_min: 0_f64,
             totalrainmm: 0_f64,
             raindayscount: 0_i64,
+            dayscount: 0_i64,
             uv_absolute: 0_f64,
             uv_mean: 0_f64,
+            baromslhpa: 0_f64,
+            baromslhpa_absolute_max: 0_f64,
+            baromslhpa_absolute_min: 0_f64,
+            baromslhpa_mean_max: 0_f64,
+            baromslhpa_mean_min: 0_f64,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct WindSpeedClass {
+    pub(crate) label: String,
+    pub(crate) minkph: f64,
+    pub(crate) maxkph: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct WindRoseSector {
+    pub(crate) direction: f64,
+    pub(crate) cardinal: String,
+    /// Percentage of all observations in each speed class, in the same order as `classes`
+    pub(crate) frequencies: Vec<f64>,
+    pub(crate) total: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct WindRose {
+    pub(crate) observations: u64,
+    pub(crate) calm: f64,
+    pub(crate) classes: Vec<WindSpeedClass>,
+    pub(crate) sectors: Vec<WindRoseSector>,
+}
+
+/// Conditions at a fixed time of day, from the observation closest to it
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct DailySnapshot {
+    pub(crate) time: DateTime<FixedOffset>,
+    pub(crate) tempc: Option<f64>,
+    pub(crate) humidity: Option<f64>,
+    pub(crate) windspeedkph: Option<f64>,
+    pub(crate) winddir: Option<f64>,
+    pub(crate) windcardinal: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct DayRecord {
+    pub(crate) date: NaiveDate,
+    /// Extremes are None if the day has no readings of them
+    pub(crate) mintemp: Option<f64>,
+    pub(crate) mintemptime: Option<DateTime<FixedOffset>>,
+    pub(crate) maxtemp: Option<f64>,
+    pub(crate) maxtemptime: Option<DateTime<FixedOffset>>,
+    /// Rain during the rain day named by this date
+    pub(crate) rainmm: f64,
+    pub(crate) maxgustkph: Option<f64>,
+    pub(crate) maxgustdir: Option<f64>,
+    pub(crate) maxgustcardinal: Option<String>,
+    pub(crate) maxgusttime: Option<DateTime<FixedOffset>>,
+    /// Solar energy in MJ/m²
+    pub(crate) solarenergy: f64,
+    pub(crate) sunshinehours: f64,
+    pub(crate) at9am: Option<DailySnapshot>,
+    pub(crate) at3pm: Option<DailySnapshot>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub(crate) struct WeatherRecord {
+    pub(crate) value: f64,
+    /// Day the record was set, or the first day of the month for monthly counts
+    pub(crate) date: NaiveDate,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub(crate) struct RecordSet {
+    pub(crate) highesttemp: Option<WeatherRecord>,
+    pub(crate) lowesttemp: Option<WeatherRecord>,
+    pub(crate) highestmintemp: Option<WeatherRecord>,
+    pub(crate) lowestmaxtemp: Option<WeatherRecord>,
+    pub(crate) strongestgust: Option<WeatherRecord>,
+    pub(crate) wettestday: Option<WeatherRecord>,
+    pub(crate) highestuv: Option<WeatherRecord>,
+    pub(crate) mostraindays: Option<WeatherRecord>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct MonthRecordSet {
+    pub(crate) month: u32,
+    #[serde(flatten)]
+    pub(crate) records: RecordSet,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct Records {
+    pub(crate) alltime: RecordSet,
+    /// Records for each calendar month, January first
+    pub(crate) months: Vec<MonthRecordSet>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum RecordScope {
+    AllTime,
+    Month,
+}
+
+/// A value today that beats a record, e.g. the highest temperature for the month
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct NewRecord {
+    pub(crate) record: String,
+    pub(crate) scope: RecordScope,
+    pub(crate) value: f64,
+    pub(crate) previous: WeatherRecord,
+}
+
+/// One row of the latest observations table, from a single observation rather than a mean
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct ObservationRow {
+    pub(crate) time: DateTime<FixedOffset>,
+    pub(crate) tempc: Option<f64>,
+    pub(crate) feelslike: Option<f64>,
+    pub(crate) dewpoint: Option<f64>,
+    pub(crate) humidity: Option<f64>,
+    pub(crate) winddir: Option<f64>,
+    pub(crate) windcardinal: Option<String>,
+    pub(crate) windspeedkph: Option<f64>,
+    pub(crate) windgustkph: Option<f64>,
+    pub(crate) baromslhpa: Option<f64>,
+    pub(crate) rainratemm: Option<f64>,
+    pub(crate) rainsince9am: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct Observation {
+    pub(crate) time: DateTime<FixedOffset>,
+    pub(crate) model: String,
+    pub(crate) tempc: Option<f64>,
+    pub(crate) tempinc: Option<f64>,
+    pub(crate) humidity: Option<f64>,
+    pub(crate) humidityin: Option<f64>,
+    pub(crate) windspeedkph: Option<f64>,
+    pub(crate) windgustkph: Option<f64>,
+    pub(crate) winddir: Option<f64>,
+    pub(crate) rainratemm: Option<f64>,
+    pub(crate) totalrainmm: Option<f64>,
+    pub(crate) dailyrainmm: Option<f64>,
+    pub(crate) eventrainmm: Option<f64>,
+    pub(crate) uv: Option<f64>,
+    pub(crate) solarradiation: Option<f64>,
+    pub(crate) baromrelhpa: Option<f64>,
+    pub(crate) baromabshpa: Option<f64>,
+}
+
+impl Default for Observation {
+    fn default() -> Self {
+        Self {
+            time: chrono::prelude::DateTime::from_timestamp(0_i64, 0_u32)
+                .unwrap()
+                .with_timezone(&FixedOffset::east_opt(0).unwrap()),
+            model: "".to_string(),
+            tempc: None,
+            tempinc: None,
+            humidity: None,
+            humidityin: None,
+            windspeedkph: None,
+            windgustkph: None,
+            winddir: None,
+            rainratemm: None,
+            totalrainmm: None,
+            dailyrainmm: None,
+            eventrainmm: None,
+            uv: None,
+            solarradiation: None,
+            baromrelhpa: None,
+            baromabshpa: None,
         }
     }
 }
+
+impl Observation {
+    pub(crate) fn fields(&self) -> Vec<(&'static str, f64)> {
+        [
+            ("tempc", self.tempc),
+            ("tempinc", self.tempinc),
+            ("humidity", self.humidity),
+            ("humidityin", self.humidityin),
+            ("windspeedkph", self.windspeedkph),
+            ("windgustkph", self.windgustkph),
+            ("winddir", self.winddir),
+            ("rainratemm", self.rainratemm),
+            ("totalrainmm", self.totalrainmm),
+            ("dailyrainmm", self.dailyrainmm),
+            ("eventrainmm", self.eventrainmm),
+            ("uv", self.uv),
+            ("solarradiation", self.solarradiation),
+            ("baromrelhpa", self.baromrelhpa),
+            ("baromabshpa", self.baromabshpa),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| value.map(|v| (name, v)))
+        .collect()
+    }
+}
@@ -0,0 +1,40 @@
+pub(crate) fn fahrenheit_to_celsius(tempf: f64) -> f64 {
+    (tempf - 32.0) * 5.0 / 9.0
+}
+
+pub(crate) fn celsius_to_fahrenheit(tempc: f64) -> f64 {
+    tempc * 9.0 / 5.0 + 32.0
+}
+
+pub(crate) fn mph_to_kph(mph: f64) -> f64 {
+    mph * 1.609344
+}
+
+pub(crate) fn inches_to_mm(inches: f64) -> f64 {
+    inches * 25.4
+}
+
+pub(crate) fn inhg_to_hpa(inhg: f64) -> f64 {
+    inhg * 33.863886666667
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-6, "{actual} != {expected}");
+    }
+
+    #[test]
+    fn converts_known_values() {
+        assert_close(fahrenheit_to_celsius(32.0), 0.0);
+        assert_close(fahrenheit_to_celsius(212.0), 100.0);
+        assert_close(fahrenheit_to_celsius(-40.0), -40.0);
+        assert_close(celsius_to_fahrenheit(37.0), 98.6);
+        assert_close(celsius_to_fahrenheit(fahrenheit_to_celsius(71.3)), 71.3);
+        assert_close(mph_to_kph(10.0), 16.09344);
+        assert_close(inches_to_mm(0.5), 12.7);
+        assert_close(inhg_to_hpa(29.92), 1013.207489);
+    }
+}
@@ -0,0 +1,111 @@
+pub(crate) const CARDINALS: [&str; 16] = [
+    "N", "NNE", "NE", "ENE", "E", "ESE", "SE", "SSE", "S", "SSW", "SW", "WSW", "W", "WNW", "NW",
+    "NNW",
+];
+
+/// Splits a wind observation into its eastward and northward components, weighted by speed
+pub(crate) fn components(speed: f64, direction: f64) -> (f64, f64) {
+    let radians = direction.to_radians();
+    (speed * radians.sin(), speed * radians.cos())
+}
+
+/// Converts the mean wind components and mean scalar speed of a window into the vector mean
+/// direction and its steadiness, the ratio of the vector mean speed to the scalar mean speed.
+/// Steadiness is 1 when the wind held one direction and approaches 0 as it varies, and calm
+/// windows report a direction and steadiness of 0.
+pub(crate) fn vector_mean(u: f64, v: f64, speed: f64) -> (f64, f64) {
+    if speed <= 0_f64 {
+        return (0_f64, 0_f64);
+    }
+    let direction = u.atan2(v).to_degrees().rem_euclid(360_f64);
+    let steadiness = (u.hypot(v) / speed).min(1_f64);
+    (direction, steadiness)
+}
+
+/// As vector_mean, for a window that may have had no wind readings, which has no direction
+pub(crate) fn window_vector_mean(
+    u: Option<f64>,
+    v: Option<f64>,
+    speed: Option<f64>,
+) -> (Option<f64>, f64) {
+    match (u, v, speed) {
+        (Some(u), Some(v), Some(speed)) => {
+            let (direction, steadiness) = vector_mean(u, v, speed);
+            (Some(direction), steadiness)
+        }
+        _ => (None, 0_f64),
+    }
+}
+
+/// Index of the 16-point compass sector containing a direction in degrees, with sector 0 (N)
+/// centred on 0°
+pub(crate) fn sector(direction: f64) -> usize {
+    (direction.rem_euclid(360_f64) / 22.5).round() as usize % CARDINALS.len()
+}
+
+/// 16-point compass label for a direction in degrees
+pub(crate) fn cardinal(direction: f64) -> &'static str {
+    CARDINALS[sector(direction)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The vector mean direction and steadiness of (speed, direction) samples
+    fn mean(samples: &[(f64, f64)]) -> (f64, f64) {
+        let count = samples.len() as f64;
+        let (u, v) = samples
+            .iter()
+            .map(|(speed, direction)| components(*speed, *direction))
+            .fold((0_f64, 0_f64), |(u, v), (su, sv)| (u + su, v + sv));
+        let speed = samples.iter().map(|(speed, _)| speed).sum::<f64>() / count;
+        vector_mean(u / count, v / count, speed)
+    }
+
+    /// Angular distance in degrees, so 359.9° is close to 0°
+    fn distance(a: f64, b: f64) -> f64 {
+        let difference = (a - b).rem_euclid(360_f64);
+        difference.min(360_f64 - difference)
+    }
+
+    #[test]
+    fn directions_either_side_of_north_average_to_north() {
+        let (direction, steadiness) = mean(&[(10.0, 350.0), (10.0, 10.0)]);
+        assert!(distance(direction, 0.0) < 1e-9, "{direction}");
+        assert!((steadiness - 10_f64.to_radians().cos()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn opposing_winds_are_unsteady() {
+        let (_, steadiness) = mean(&[(10.0, 90.0), (10.0, 270.0)]);
+        assert!(steadiness < 1e-9);
+    }
+
+    #[test]
+    fn calm_samples() {
+        assert_eq!(mean(&[(0.0, 0.0), (0.0, 180.0)]), (0.0, 0.0));
+        // A calm sample has no direction, so only lowers the steadiness
+        let (direction, steadiness) = mean(&[(10.0, 90.0), (0.0, 270.0)]);
+        assert!(distance(direction, 90.0) < 1e-9);
+        assert!((steadiness - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn speeds_are_weighted() {
+        // The stronger westerly outweighs the light easterly
+        let (direction, _) = mean(&[(20.0, 270.0), (5.0, 90.0)]);
+        assert!(distance(direction, 270.0) < 1e-9);
+        let (direction, _) = mean(&[(30.0, 0.0), (10.0, 90.0)]);
+        assert!(distance(direction, 18.43) < 0.01, "{direction}");
+    }
+
+    #[test]
+    fn sectors_wrap_around_north() {
+        assert_eq!(cardinal(355.0), "N");
+        assert_eq!(cardinal(11.0), "N");
+        assert_eq!(cardinal(12.0), "NNE");
+        assert_eq!(cardinal(-90.0), "W");
+        assert_eq!(cardinal(202.5), "SSW");
+    }
+}
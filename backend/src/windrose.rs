@@ -0,0 +1,210 @@
+use crate::resolution::Resolution;
+use crate::types::{HourRecordFlux, WindRose, WindRoseSector, WindSpeedClass};
+use crate::wind::{CARDINALS, sector};
+use crate::{ApiError, RangeParams, ServerState, parse_range_params};
+use axum::Json;
+use axum::extract::{Query, State};
+use serde::Deserialize;
+use std::sync::Arc;
+
+// Beaufort force 0 is below 1 km/h, which is treated as calm on either scale
+const CALM_KPH: f64 = 1_f64;
+
+// Lower bound of each class in km/h, excluding calm
+const BOM_CLASSES: [f64; 5] = [1_f64, 10_f64, 20_f64, 30_f64, 40_f64];
+const BEAUFORT_CLASSES: [f64; 12] = [
+    1_f64, 6_f64, 12_f64, 20_f64, 29_f64, 39_f64, 50_f64, 62_f64, 75_f64, 89_f64, 103_f64, 118_f64,
+];
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum SpeedScale {
+    /// The 10 km/h classes used by BOM wind roses
+    #[default]
+    Bom,
+    Beaufort,
+}
+
+impl SpeedScale {
+    fn bounds(&self) -> &'static [f64] {
+        match self {
+            SpeedScale::Bom => &BOM_CLASSES,
+            SpeedScale::Beaufort => &BEAUFORT_CLASSES,
+        }
+    }
+
+    fn classes(&self) -> Vec<WindSpeedClass> {
+        let bounds = self.bounds();
+        bounds
+            .iter()
+            .enumerate()
+            .map(|(index, min)| {
+                let max = bounds.get(index + 1).copied();
+                let label = match (self, max) {
+                    (SpeedScale::Beaufort, _) => format!("Force {}", index + 1),
+                    (SpeedScale::Bom, Some(max)) => format!("{min}-{max} km/h"),
+                    (SpeedScale::Bom, None) => format!("{min}+ km/h"),
+                };
+                WindSpeedClass {
+                    label,
+                    minkph: *min,
+                    maxkph: max,
+                }
+            })
+            .collect()
+    }
+
+    fn class(&self, speed: f64) -> usize {
+        self.bounds()
+            .iter()
+            .rposition(|min| speed >= *min)
+            .unwrap_or(0)
+    }
+}
+
+#[derive(Deserialize)]
+pub(crate) struct WindRoseParams {
+    #[serde(flatten)]
+    range: RangeParams,
+    #[serde(default)]
+    scale: SpeedScale,
+}
+
+fn percentage(count: u64, total: u64) -> f64 {
+    if total == 0 {
+        return 0_f64;
+    }
+    count as f64 * 100_f64 / total as f64
+}
+
+fn wind_rose(data: &[HourRecordFlux], scale: SpeedScale) -> WindRose {
+    let classes = scale.classes();
+    let mut counts = vec![vec![0_u64; classes.len()]; CARDINALS.len()];
+    let mut calm = 0_u64;
+    let mut observations = 0_u64;
+    // Observations missing a reading are left out, rather than counted as calm or northerly. A
+    // calm observation doesn't need a direction.
+    for datum in data {
+        let Some(windspeedkph) = datum.windspeedkph else {
+            continue;
+        };
+        if windspeedkph < CALM_KPH {
+            calm += 1;
+        } else if let Some(winddir) = datum.winddir {
+            counts[sector(winddir)][scale.class(windspeedkph)] += 1;
+        } else {
+            continue;
+        }
+        observations += 1;
+    }
+
+    WindRose {
+        observations,
+        calm: percentage(calm, observations),
+        classes,
+        sectors: counts
+            .iter()
+            .enumerate()
+            .map(|(index, counts)| WindRoseSector {
+                direction: index as f64 * 22.5,
+                cardinal: CARDINALS[index].to_string(),
+                frequencies: counts
+                    .iter()
+                    .map(|count| percentage(*count, observations))
+                    .collect(),
+                total: percentage(counts.iter().sum(), observations),
+            })
+            .collect(),
+    }
+}
+
+pub(crate) async fn windrose(
+    State(state): State<Arc<ServerState>>,
+    Query(params): Query<WindRoseParams>,
+) -> Result<Json<WindRose>, ApiError> {
+    let (start, end) = parse_range_params(&params.range)?;
+    // Individual observations are used, as averaging would hide gusty and variable periods
+    let data = state.store.range(start, end, Resolution::Raw).await?;
+
+    Ok(Json(wind_rose(&data, params.scale)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn observation(windspeedkph: Option<f64>, winddir: Option<f64>) -> HourRecordFlux {
+        HourRecordFlux {
+            windspeedkph,
+            winddir,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn classes() {
+        assert_eq!(SpeedScale::Bom.class(1_f64), 0);
+        assert_eq!(SpeedScale::Bom.class(9.9), 0);
+        assert_eq!(SpeedScale::Bom.class(10_f64), 1);
+        assert_eq!(SpeedScale::Bom.class(65_f64), 4);
+        assert_eq!(SpeedScale::Beaufort.class(5_f64), 0);
+        assert_eq!(SpeedScale::Beaufort.class(30_f64), 4);
+        assert_eq!(SpeedScale::Beaufort.class(120_f64), 11);
+
+        let classes = SpeedScale::Bom.classes();
+        assert_eq!(classes[0].label, "1-10 km/h");
+        assert_eq!(classes[4].label, "40+ km/h");
+        assert_eq!(classes[4].maxkph, None);
+        assert_eq!(SpeedScale::Beaufort.classes()[11].label, "Force 12");
+    }
+
+    #[test]
+    fn bins_by_sector_and_speed() {
+        let data = [
+            observation(Some(0.5), None),
+            observation(Some(15_f64), Some(0_f64)),
+            observation(Some(15_f64), Some(350_f64)),
+            observation(Some(45_f64), Some(92_f64)),
+            observation(Some(5_f64), Some(180_f64)),
+        ];
+        let rose = wind_rose(&data, SpeedScale::Bom);
+
+        assert_eq!(rose.observations, 5);
+        assert_eq!(rose.calm, 20_f64);
+        assert_eq!(rose.sectors[0].cardinal, "N");
+        assert_eq!(
+            rose.sectors[0].frequencies,
+            [0_f64, 40_f64, 0_f64, 0_f64, 0_f64]
+        );
+        assert_eq!(rose.sectors[0].total, 40_f64);
+        assert_eq!(rose.sectors[4].cardinal, "E");
+        assert_eq!(rose.sectors[4].frequencies[4], 20_f64);
+        assert_eq!(rose.sectors[8].frequencies[0], 20_f64);
+        let total: f64 = rose.sectors.iter().map(|sector| sector.total).sum();
+        assert_eq!(total + rose.calm, 100_f64);
+    }
+
+    #[test]
+    fn skips_missing_wind() {
+        let data = [
+            observation(None, None),
+            observation(None, Some(90_f64)),
+            observation(Some(20_f64), None),
+            observation(Some(20_f64), Some(90_f64)),
+        ];
+        let rose = wind_rose(&data, SpeedScale::Bom);
+
+        assert_eq!(rose.observations, 1);
+        assert_eq!(rose.calm, 0_f64);
+        assert_eq!(rose.sectors[4].frequencies[2], 100_f64);
+    }
+
+    #[test]
+    fn no_observations() {
+        let rose = wind_rose(&[observation(None, None)], SpeedScale::Beaufort);
+
+        assert_eq!(rose.observations, 0);
+        assert_eq!(rose.calm, 0_f64);
+        assert!(rose.sectors.iter().all(|sector| sector.total == 0_f64));
+    }
+}
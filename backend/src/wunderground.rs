@@ -0,0 +1,87 @@
+use crate::ingest::{parse_date_utc, parse_param, sanitise, write_observation};
+use crate::types::Observation;
+use crate::units::{fahrenheit_to_celsius, inches_to_mm, inhg_to_hpa, mph_to_kph};
+use crate::{ApiError, ServerState};
+use axum::extract::{Query, State};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+pub(crate) fn parse_wunderground(params: &HashMap<String, String>) -> Observation {
+    Observation {
+        time: parse_date_utc(params),
+        model: params
+            .get("softwaretype")
+            .cloned()
+            .unwrap_or("wunderground".to_string()),
+        tempc: parse_param(params, "tempf").map(fahrenheit_to_celsius),
+        tempinc: parse_param(params, "indoortempf").map(fahrenheit_to_celsius),
+        humidity: parse_param(params, "humidity"),
+        humidityin: parse_param(params, "indoorhumidity"),
+        windspeedkph: parse_param(params, "windspeedmph").map(mph_to_kph),
+        windgustkph: parse_param(params, "windgustmph").map(mph_to_kph),
+        winddir: parse_param(params, "winddir"),
+        // rainin is the rain over the past hour rather than an instantaneous rate, so only the
+        // rainratein sent by some Ecowitt consoles is used
+        rainratemm: parse_param(params, "rainratein").map(inches_to_mm),
+        // As on the Ecowitt path, rain is worked out from whichever counters were sent
+        totalrainmm: parse_param(params, "totalrainin").map(inches_to_mm),
+        dailyrainmm: parse_param(params, "dailyrainin").map(inches_to_mm),
+        eventrainmm: None,
+        uv: parse_param(params, "UV"),
+        solarradiation: parse_param(params, "solarradiation"),
+        baromrelhpa: parse_param(params, "baromin").map(inhg_to_hpa),
+        baromabshpa: parse_param(params, "absbaromin").map(inhg_to_hpa),
+    }
+}
+
+pub(crate) async fn wunderground(
+    State(state): State<Arc<ServerState>>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<&'static str, ApiError> {
+    if let Some(station_id) = &state.wu_station_id
+        && params.get("ID") != Some(station_id)
+    {
+        return Err(ApiError::Unauthorised);
+    }
+    if let Some(password) = &state.wu_password
+        && params.get("PASSWORD") != Some(password)
+    {
+        return Err(ApiError::Unauthorised);
+    }
+
+    let observation = sanitise(parse_wunderground(&params));
+    write_observation(&state, observation).await?;
+
+    Ok("success\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(values: &[(&str, &str)]) -> HashMap<String, String> {
+        values
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn hourly_rain_is_not_a_rate() {
+        let observation = parse_wunderground(&params(&[("rainin", "0.5"), ("dailyrainin", "1")]));
+        assert_eq!(observation.rainratemm, None);
+        assert_eq!(observation.dailyrainmm, Some(25.4));
+        assert_eq!(observation.totalrainmm, None);
+    }
+
+    #[test]
+    fn rain_rate_and_total_when_sent() {
+        let observation = parse_wunderground(&params(&[
+            ("rainratein", "0.1"),
+            ("dailyrainin", "1"),
+            ("totalrainin", "10"),
+        ]));
+        assert!((observation.rainratemm.unwrap() - 2.54).abs() < 1e-9);
+        assert_eq!(observation.totalrainmm, Some(254.0));
+    }
+}
@@ -0,0 +1,69 @@
+use crate::stats::{DaySummary, month_records, year_records};
+use crate::types::{WeatherRecord, YearRecord};
+use crate::{ApiError, RangeParams, ServerState, parse_range_params};
+use axum::Json;
+use axum::extract::{Query, State};
+use chrono::Datelike;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+const HOT_DAY_TEMPC: f64 = 30_f64;
+const FROST_DAY_TEMPC: f64 = 0_f64;
+
+#[derive(Default)]
+struct YearCounts {
+    hotdayscount: i64,
+    frostdayscount: i64,
+    wettestmonth: Option<WeatherRecord>,
+}
+
+/// Yearly statistics from daily summaries, which must be sorted by date
+fn yearly_records(days: &[DaySummary]) -> Vec<YearRecord> {
+    let mut counts: BTreeMap<i32, YearCounts> = BTreeMap::new();
+    for day in days.iter().filter(|day| day.tempc.count > 0) {
+        let year = counts.entry(day.date.year()).or_default();
+        if day.tempc.max > HOT_DAY_TEMPC {
+            year.hotdayscount += 1;
+        }
+        if day.tempc.min < FROST_DAY_TEMPC {
+            year.frostdayscount += 1;
+        }
+    }
+    for month in month_records(days) {
+        if month.totalrainmm <= 0_f64 {
+            continue;
+        }
+        let year = counts.entry(month.time.year()).or_default();
+        if year
+            .wettestmonth
+            .is_none_or(|wettest| month.totalrainmm > wettest.value)
+        {
+            year.wettestmonth = Some(WeatherRecord {
+                value: month.totalrainmm,
+                date: month.time.date_naive(),
+            });
+        }
+    }
+
+    year_records(days)
+        .into_iter()
+        .map(|record| {
+            let counts = counts.remove(&record.time.year()).unwrap_or_default();
+            YearRecord {
+                record,
+                hotdayscount: counts.hotdayscount,
+                frostdayscount: counts.frostdayscount,
+                wettestmonth: counts.wettestmonth,
+            }
+        })
+        .collect()
+}
+
+pub(crate) async fn yearly(
+    State(state): State<Arc<ServerState>>,
+    Query(params): Query<RangeParams>,
+) -> Result<Json<Vec<YearRecord>>, ApiError> {
+    let (start, end) = parse_range_params(&params)?;
+    let days = state.rollups.range(&state, start, end).await?;
+    Ok(Json(yearly_records(&days)))
+}
@@ -9,6 +9,7 @@ export const normaliseTodayData = (todayData: TodayData): TodayData => {
         sunrise: todayData.sunrise,
         sunset: todayData.sunset,
         maxuv: round(1, todayData.maxuv),
+        rainsince9am: round(2, todayData.rainsince9am),
     });
 }
 
@@ -28,6 +29,14 @@ export const normaliseHourRecord = (hourRecord: HourRecord): HourRecord => {
         solarradiation: round(1, hourRecord.solarradiation),
         feelslike: round(1, hourRecord.feelslike),
         feelslikein: round(1, hourRecord.feelslikein),
+        dewpoint: round(1, hourRecord.dewpoint),
+        heatindex: round(1, hourRecord.heatindex),
+        windchill: round(1, hourRecord.windchill),
+        humidex: round(1, hourRecord.humidex),
+        wetbulb: round(1, hourRecord.wetbulb),
+        absolutehumidity: round(1, hourRecord.absolutehumidity),
+        cloudbase: round(0, hourRecord.cloudbase),
+        sky: hourRecord.sky,
     };
 };
 
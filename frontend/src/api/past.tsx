@@ -49,6 +49,7 @@ const loadPastDataForRange = async (start: Temporal.Instant, end: Temporal.Insta
     const url = new URL('/api/past', window.location.origin);
     url.searchParams.set('start', start.toZonedDateTimeISO(Temporal.Now.timeZoneId()).toString({ timeZoneName: 'never' }));
     url.searchParams.set('end', end.toZonedDateTimeISO(Temporal.Now.timeZoneId()).toString({ timeZoneName: 'never' }));
+    url.searchParams.set('resolution', '1h');
     const res = await fetch(url.toString());
     return (await res.json())
         .map(normaliseHourRecord);
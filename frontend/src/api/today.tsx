@@ -13,6 +13,7 @@ const mockValuesForTesting: TodayData = {
   winddir: 0.0,
   rainratemm: 0,
   totalrainmm: 0,
+  rainsince9am: 0,
   uv: 4,
   mintemp: 18,
   maxtemp: 22,
@@ -1,3 +1,5 @@
+export type Sky = 'sunny' | 'partly_cloudy' | 'cloudy' | 'night' | 'rain' | 'showers';
+
 export interface HourRecord {
   time: string; // ISO string
   tempc?: number;
@@ -13,6 +15,14 @@ export interface HourRecord {
   solarradiation?: number;
   feelslike?: number;
   feelslikein?: number;
+  dewpoint?: number;
+  heatindex?: number;
+  windchill?: number;
+  humidex?: number;
+  wetbulb?: number;
+  absolutehumidity?: number;
+  cloudbase?: number;
+  sky?: Sky;
 }
 
 export interface TodayData extends HourRecord {
@@ -23,6 +33,7 @@ export interface TodayData extends HourRecord {
   sunrise?: string;
   sunset?: string;
   maxuv?: number;
+  rainsince9am?: number;
 }
 
 export interface MonthRecord {
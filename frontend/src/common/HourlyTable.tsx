@@ -13,7 +13,7 @@ import {
 } from '@mui/material';
 import { Temporal } from 'temporal-polyfill';
 
-import { dewPoint, isNight } from '../util';
+import { isNight } from '../util';
 import type { HourRecord } from '../api';
 
 import { loadPastDataForRange } from '../api';
@@ -220,7 +220,7 @@ const HourlyTable: React.FC<{ start: Temporal.Instant, end: Temporal.Instant, su
                                 {
                                     forEachHour((hour, dayIndex) => (
                                         <DayTableCell key={hour.time} dayIndex={dayIndex} style={{ borderBottom: 'none' }}>
-                                            <WeatherIcon record={hour} hour={dayIndex} size='medium' />
+                                            <WeatherIcon record={hour} size='medium' />
                                         </DayTableCell>
                                     ))
                                 }
@@ -352,7 +352,7 @@ const OutdoorTemperatureRows: React.FC<TableFragment> = ({ forEachDay, forEachHo
                 {
                     forEachHour((hour, dayIndex) => (
                         <DayTableCell key={hour.time} dayIndex={dayIndex} style={{ borderBottom: 'none' }}>
-                            <WeatherIcon record={hour} hour={dayIndex} size='medium' />
+                            <WeatherIcon record={hour} size='medium' />
                         </DayTableCell>
                     ))
                 }
@@ -625,7 +625,7 @@ const OtherRows: React.FC<TableFragment> = ({ forEachDay, forEachHour }) => (
             {
                 forEachHour((hour, dayIndex) => (
                     <DayTableCell key={hour.time} dayIndex={dayIndex}>
-                        {dewPoint(hour.tempc, hour.humidity)} °C
+                        {hour.dewpoint} °C
                     </DayTableCell>
                 ))
             }
@@ -11,7 +11,6 @@ import SunnySnowingIcon from '@mui/icons-material/SunnySnowing';
 import Loading from '../common/Loading.tsx';
 import { WindDirectionArrow, WindDirectionName } from '../common/WindDirection.tsx';
 import { loadToday } from '../api';
-import { dewPoint } from '../util';
 import UvIndex from '../common/UvIndex.tsx';
 import InlineIcon from '../common/InlineIcon.tsx';
 import { Temporal } from 'temporal-polyfill';
@@ -52,7 +51,10 @@ const TodayCard: React.FC = () => {
                             <b><NumberOrDefault value={todayData.windspeedkph} defaultValue='--' /> kph <WindDirectionArrow direction={todayData.winddir} /><WindDirectionName direction={todayData.winddir} /></b>&nbsp;&nbsp;Wind
                         </Typography>
                         <Typography variant="body1">
-                            <b><NumberOrDefault value={todayData.totalrainmm} defaultValue='--' /> mm</b>&nbsp;&nbsp;Rain since midnight
+                            <b><NumberOrDefault value={todayData.totalrainmm} defaultValue='--' /> mm</b>&nbsp;&nbsp;Rain today
+                        </Typography>
+                        <Typography variant="body1">
+                            <b><NumberOrDefault value={todayData.rainsince9am} defaultValue='--' /> mm</b>&nbsp;&nbsp;Rain since 9am
                         </Typography>
                     </Grid>
                     <Grid size={{ xs: 12, md: 4 }}
@@ -64,7 +66,7 @@ const TodayCard: React.FC = () => {
                             <b><NumberOrDefault value={todayData.humidityin} defaultValue='--' />%</b>&nbsp;&nbsp;Indoor Humidity
                         </Typography>
                         <Typography variant="body1">
-                            <b>{dewPoint(todayData.tempc, todayData.humidity)}°C</b>&nbsp;&nbsp;Dew Point
+                            <b><NumberOrDefault value={todayData.dewpoint} defaultValue='--' />°C</b>&nbsp;&nbsp;Dew Point
                         </Typography>
                     </Grid>
 
@@ -1 +1 @@
-export { isNight, dewPoint } from './derive.tsx';
+export { isNight } from './derive.tsx';